@@ -0,0 +1,503 @@
+use std::{collections::HashMap, path::Path};
+
+use ethers::{
+    abi::{Function, Param, ParamType, StateMutability, Token},
+    types::{Address, Bytes, I256, U256},
+};
+
+use crate::{
+    client::ClientResult,
+    contracts::{is_multisend, multisend_calldata, unpack_multisend, MULTISEND_CONTRACT},
+    rpc::{
+        common::{ChecksumAddress, Operations},
+        msig_history::MsigTxResponse,
+        propose::{MetaTransactionData, SafeTransactionData},
+    },
+    SafeClient,
+};
+
+/// Transaction Builder file format version
+pub const BATCH_VERSION: &str = "1.0";
+
+/// Transaction Builder batch errors
+#[derive(Debug, thiserror::Error)]
+pub enum BatchError {
+    /// Filesystem error
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    /// Invalid JSON
+    #[error("{0}")]
+    SerdeError(#[from] serde_json::Error),
+    /// ABI encoding error
+    #[error("{0}")]
+    Abi(#[from] ethers::abi::Error),
+    /// A transaction has neither data nor a contract method
+    #[error("Transaction {index} has neither data nor a contract method")]
+    MissingData {
+        /// Index of the transaction in the batch
+        index: usize,
+    },
+    /// A contract method input has no value
+    #[error("Transaction {index}: missing value for input {input:?}")]
+    MissingInput {
+        /// Index of the transaction in the batch
+        index: usize,
+        /// The input name
+        input: String,
+    },
+    /// A contract method input value does not match its type
+    #[error("Transaction {index}: invalid value {value} for input {input:?} of type {kind}")]
+    InvalidInput {
+        /// Index of the transaction in the batch
+        index: usize,
+        /// The input name
+        input: String,
+        /// The input type
+        kind: String,
+        /// The value
+        value: serde_json::Value,
+    },
+    /// Transaction Builder batches contain only calls
+    #[error("Transaction {index} is a delegatecall, which batches cannot express")]
+    DelegateCall {
+        /// Index of the transaction in the batch
+        index: usize,
+    },
+    /// A MultiSend transaction could not be unpacked
+    #[error("Malformed multiSend calldata")]
+    MalformedMultiSend,
+}
+
+/// Metadata of a Transaction Builder batch
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchMeta {
+    /// The batch name
+    #[serde(default)]
+    pub name: Option<String>,
+    /// The batch description
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Version of the Transaction Builder that created the batch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_builder_version: Option<String>,
+    /// The Safe the batch was created for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_from_safe_address: Option<String>,
+    /// The owner that created the batch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_from_owner_address: Option<String>,
+    /// Checksum computed by the Transaction Builder. Preserved, but not
+    /// verified or computed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+}
+
+/// A contract method of a Transaction Builder transaction
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ContractMethod {
+    /// The method name
+    pub name: String,
+    /// The method inputs
+    pub inputs: Vec<Param>,
+    /// True if the method is payable
+    #[serde(default)]
+    pub payable: bool,
+}
+
+impl ContractMethod {
+    fn function(&self) -> Function {
+        #[allow(deprecated)]
+        Function {
+            name: self.name.clone(),
+            inputs: self.inputs.clone(),
+            outputs: vec![],
+            constant: None,
+            state_mutability: if self.payable {
+                StateMutability::Payable
+            } else {
+                StateMutability::NonPayable
+            },
+        }
+    }
+}
+
+/// A transaction of a Transaction Builder batch. Its calldata is either raw
+/// `data`, or a `contractMethod` and its `contractInputsValues`
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchTransaction {
+    /// Target of the transaction
+    pub to: ChecksumAddress,
+    /// Native asset value, in wei
+    #[serde(default, with = "crate::rpc::common::dec_u256_ser")]
+    pub value: U256,
+    /// Raw calldata
+    #[serde(default)]
+    pub data: Option<Bytes>,
+    /// The called method, if built from an ABI
+    #[serde(default)]
+    pub contract_method: Option<ContractMethod>,
+    /// The method's argument values by input name, as entered in the
+    /// Transaction Builder
+    #[serde(default)]
+    pub contract_inputs_values: Option<HashMap<String, serde_json::Value>>,
+}
+
+impl From<&MetaTransactionData> for BatchTransaction {
+    fn from(tx: &MetaTransactionData) -> Self {
+        Self {
+            to: tx.to,
+            value: tx.value,
+            data: Some(tx.data.clone().unwrap_or_default()),
+            contract_method: None,
+            contract_inputs_values: None,
+        }
+    }
+}
+
+impl BatchTransaction {
+    /// Convert to a call, ABI encoding the contract method if there is no raw
+    /// data. `index` is the transaction's position in its batch, for errors
+    pub fn to_meta_tx(&self, index: usize) -> Result<MetaTransactionData, BatchError> {
+        let data = match (&self.data, &self.contract_method) {
+            (Some(data), _) if !data.is_empty() => data.clone(),
+            (_, Some(method)) => self.encode_method(index, method)?,
+            (Some(data), None) => data.clone(),
+            (None, None) => return Err(BatchError::MissingData { index }),
+        };
+        Ok(MetaTransactionData {
+            to: self.to,
+            value: self.value,
+            data: (!data.is_empty()).then_some(data),
+            operation: Some(Operations::Call),
+        })
+    }
+
+    fn encode_method(&self, index: usize, method: &ContractMethod) -> Result<Bytes, BatchError> {
+        let values = self.contract_inputs_values.as_ref();
+        let tokens = method
+            .inputs
+            .iter()
+            .map(|input| {
+                let value = values.and_then(|v| v.get(&input.name)).ok_or_else(|| {
+                    BatchError::MissingInput {
+                        index,
+                        input: input.name.clone(),
+                    }
+                })?;
+                tokenize(&input.kind, value).ok_or_else(|| BatchError::InvalidInput {
+                    index,
+                    input: input.name.clone(),
+                    kind: input.kind.to_string(),
+                    value: value.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(method.function().encode_input(&tokens)?.into())
+    }
+}
+
+/// Tokenize a Transaction Builder input value. Scalars are strings, with
+/// integers in decimal. Arrays & tuples are JSON arrays, or strings holding
+/// them
+fn tokenize(kind: &ParamType, value: &serde_json::Value) -> Option<Token> {
+    use serde_json::Value;
+
+    let nested = |value: &Value| -> Option<Vec<Value>> {
+        match value {
+            Value::Array(items) => Some(items.clone()),
+            Value::String(s) => serde_json::from_str(s).ok(),
+            _ => None,
+        }
+    };
+    let text = match value {
+        Value::String(s) => s.trim().to_owned(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        _ => String::new(),
+    };
+
+    Some(match kind {
+        ParamType::Address => Token::Address(text.parse().ok()?),
+        ParamType::Uint(_) => Token::Uint(match text.strip_prefix("0x") {
+            Some(hex) => U256::from_str_radix(hex, 16).ok()?,
+            None => U256::from_dec_str(&text).ok()?,
+        }),
+        ParamType::Int(_) => Token::Int(I256::from_dec_str(&text).ok()?.into_raw()),
+        ParamType::Bool => Token::Bool(match text.as_str() {
+            "true" => true,
+            "false" => false,
+            _ => return None,
+        }),
+        ParamType::String => Token::String(text),
+        ParamType::Bytes => Token::Bytes(text.parse::<Bytes>().ok()?.to_vec()),
+        ParamType::FixedBytes(len) => {
+            let bytes = text.parse::<Bytes>().ok()?;
+            if bytes.len() != *len {
+                return None;
+            }
+            Token::FixedBytes(bytes.to_vec())
+        }
+        ParamType::Array(inner) => Token::Array(
+            nested(value)?
+                .iter()
+                .map(|item| tokenize(inner, item))
+                .collect::<Option<_>>()?,
+        ),
+        ParamType::FixedArray(inner, len) => {
+            let items = nested(value)?;
+            if items.len() != *len {
+                return None;
+            }
+            Token::FixedArray(
+                items
+                    .iter()
+                    .map(|item| tokenize(inner, item))
+                    .collect::<Option<_>>()?,
+            )
+        }
+        ParamType::Tuple(kinds) => {
+            let items = nested(value)?;
+            if items.len() != kinds.len() {
+                return None;
+            }
+            Token::Tuple(
+                kinds
+                    .iter()
+                    .zip(items.iter())
+                    .map(|(kind, item)| tokenize(kind, item))
+                    .collect::<Option<_>>()?,
+            )
+        }
+    })
+}
+
+/// A batch in the Safe web app's Transaction Builder JSON format
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchFile {
+    /// File format version
+    pub version: String,
+    /// Chain ID, as a decimal string
+    pub chain_id: String,
+    /// Creation time, in milliseconds since the epoch
+    pub created_at: u64,
+    /// Batch metadata
+    pub meta: BatchMeta,
+    /// The transactions
+    pub transactions: Vec<BatchTransaction>,
+}
+
+impl BatchFile {
+    /// Create a batch of calls for the Safe at `safe_address`. Errors if any
+    /// transaction is a delegatecall
+    pub fn new<'a>(
+        chain_id: u64,
+        safe_address: Address,
+        txns: impl IntoIterator<Item = &'a MetaTransactionData>,
+    ) -> Result<Self, BatchError> {
+        let transactions = txns
+            .into_iter()
+            .enumerate()
+            .map(|(index, tx)| match tx.operation {
+                Some(Operations::DelegateCall) => Err(BatchError::DelegateCall { index }),
+                _ => Ok(BatchTransaction::from(tx)),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            version: BATCH_VERSION.to_owned(),
+            chain_id: chain_id.to_string(),
+            created_at: chrono::Utc::now().timestamp_millis() as u64,
+            meta: BatchMeta {
+                name: Some("Transactions Batch".to_owned()),
+                created_from_safe_address: Some(ChecksumAddress::from(safe_address).to_string()),
+                ..Default::default()
+            },
+            transactions,
+        })
+    }
+
+    /// Create a batch from a Safe transaction. MultiSend transactions are
+    /// unpacked into their calls. Other transactions must be calls
+    pub fn from_safe_tx(
+        chain_id: u64,
+        safe_address: Address,
+        tx: &SafeTransactionData,
+    ) -> Result<Self, BatchError> {
+        let to = Address::from(tx.core.to);
+        match tx.core.operation {
+            Some(Operations::DelegateCall) if is_multisend(to) => {
+                let data = tx.core.data.as_deref().unwrap_or_default();
+                let txns = MULTISEND_CONTRACT
+                    .decode::<Bytes, _>("multiSend", data)
+                    .ok()
+                    .and_then(|packed| unpack_multisend(&packed))
+                    .ok_or(BatchError::MalformedMultiSend)?;
+                Self::new(chain_id, safe_address, &txns)
+            }
+            _ => Self::new(chain_id, safe_address, std::iter::once(&tx.core)),
+        }
+    }
+
+    /// Create a batch from a multisig transaction. See
+    /// [`BatchFile::from_safe_tx`]
+    pub fn from_msig_tx(chain_id: u64, tx: &MsigTxResponse) -> Result<Self, BatchError> {
+        let mut batch = Self::from_safe_tx(chain_id, tx.safe, &SafeTransactionData::from(tx))?;
+        batch.meta.description = Some(format!(
+            "Safe transaction {:?} at nonce {}",
+            tx.safe_tx_hash, tx.nonce
+        ));
+        Ok(batch)
+    }
+
+    /// Parse a batch from JSON
+    pub fn from_json(json: &str) -> Result<Self, BatchError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Read a batch from a JSON file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, BatchError> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// Serialize to pretty JSON
+    pub fn to_json(&self) -> Result<String, BatchError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Convert to calls, ABI encoding contract methods
+    pub fn to_meta_txns(&self) -> Result<Vec<MetaTransactionData>, BatchError> {
+        self.transactions
+            .iter()
+            .enumerate()
+            .map(|(index, tx)| tx.to_meta_tx(index))
+            .collect()
+    }
+
+    /// Convert to a single delegatecall to the MultiSend deployment at
+    /// `multisend`, executing all calls atomically
+    pub fn to_multisend(&self, multisend: Address) -> Result<MetaTransactionData, BatchError> {
+        Ok(MetaTransactionData {
+            to: multisend.into(),
+            value: U256::zero(),
+            data: Some(multisend_calldata(&self.to_meta_txns()?)),
+            operation: Some(Operations::DelegateCall),
+        })
+    }
+}
+
+impl SafeClient {
+    /// Export the Safe's pending MultiSend proposals as Transaction Builder
+    /// batches. Proposals that batches cannot express are skipped
+    pub async fn pending_batches(
+        &self,
+        safe_address: Address,
+    ) -> ClientResult<Vec<(MsigTxResponse, BatchFile)>> {
+        let chain_id = self.network().chain_id;
+        Ok(self
+            .queue(safe_address)
+            .await?
+            .nonces
+            .into_iter()
+            .flat_map(|n| n.transactions)
+            .filter(|pending| {
+                pending.tx.operation == Operations::DelegateCall && is_multisend(pending.tx.to)
+            })
+            .filter_map(|pending| {
+                let batch = BatchFile::from_msig_tx(chain_id, &pending.tx).ok()?;
+                Some((pending.tx, batch))
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{contracts::MULTISEND_ADDRESSES, tx::erc20_transfer};
+
+    #[test]
+    fn it_round_trips_batches() {
+        let usdc: Address = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"
+            .parse()
+            .unwrap();
+        let alice = Address::repeat_byte(0xa1);
+        let json = r#"{
+            "version": "1.0",
+            "chainId": "1",
+            "createdAt": 1692000000000,
+            "meta": {
+                "name": "Transactions Batch",
+                "description": "",
+                "txBuilderVersion": "1.16.1",
+                "createdFromSafeAddress": "0x5a5A5a5a5A5a5a5a5a5A5a5A5A5a5a5A5A5A5A5A",
+                "createdFromOwnerAddress": "",
+                "checksum": "0x00"
+            },
+            "transactions": [
+                {
+                    "to": "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+                    "value": "0",
+                    "data": null,
+                    "contractMethod": {
+                        "inputs": [
+                            { "internalType": "address", "name": "to", "type": "address" },
+                            { "internalType": "uint256", "name": "value", "type": "uint256" }
+                        ],
+                        "name": "transfer",
+                        "payable": false
+                    },
+                    "contractInputsValues": {
+                        "to": "0xa1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1",
+                        "value": "1500000"
+                    }
+                },
+                {
+                    "to": "0xa1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1",
+                    "value": "1000000000000000000",
+                    "data": "0x",
+                    "contractMethod": null,
+                    "contractInputsValues": null
+                }
+            ]
+        }"#;
+
+        let batch = BatchFile::from_json(json).unwrap();
+        let txns = batch.to_meta_txns().unwrap();
+        assert_eq!(txns[0], erc20_transfer(usdc, alice, 1_500_000.into()));
+        assert_eq!(txns[1].value, U256::exp10(18));
+        assert_eq!(txns[1].data, None);
+
+        let safe = Address::repeat_byte(0x5a);
+        let tx = SafeTransactionData {
+            core: batch.to_multisend(MULTISEND_ADDRESSES[1]).unwrap(),
+            gas: Default::default(),
+            nonce: 0,
+        };
+        let exported = BatchFile::from_safe_tx(1, safe, &tx).unwrap();
+        let exported = BatchFile::from_json(&exported.to_json().unwrap()).unwrap();
+        assert_eq!(exported.to_meta_txns().unwrap(), txns);
+    }
+
+    #[test]
+    fn it_tokenizes_inputs() {
+        let kind = ParamType::Array(Box::new(ParamType::Address));
+        let value = serde_json::json!("[\"0xa1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1\"]");
+        assert_eq!(
+            tokenize(&kind, &value),
+            Some(Token::Array(vec![Token::Address(Address::repeat_byte(
+                0xa1
+            ))]))
+        );
+        let kind = ParamType::Tuple(vec![ParamType::Int(256), ParamType::Bool]);
+        assert_eq!(
+            tokenize(&kind, &serde_json::json!("[\"-1\", \"true\"]")),
+            Some(Token::Tuple(vec![Token::Int(U256::MAX), Token::Bool(true)]))
+        );
+        assert_eq!(
+            tokenize(&ParamType::Uint(256), &serde_json::json!("1.5")),
+            None
+        );
+    }
+}
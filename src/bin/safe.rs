@@ -0,0 +1,791 @@
+//! `safe`: command-line client for day-to-day Safe operations
+
+use std::{error::Error, path::PathBuf, sync::Arc};
+
+use clap::{Args, Parser, Subcommand};
+use ethers::{
+    middleware::SignerMiddleware,
+    providers::{Http, Middleware, Provider},
+    signers::{LocalWallet, Signer},
+    types::{Address, Bytes, Signature, TransactionRequest, H256, U256},
+};
+use safe_sdk::{
+    batch::BatchFile,
+    contracts::{exec_transaction_calldata, execution_success, multisend_call_only},
+    decode::Decoder,
+    gas::estimate_safe_tx_gas,
+    networks::TxService,
+    preview::Previewer,
+    rpc::{
+        common::Operations,
+        msig_history::MsigTxResponse,
+        propose::{MetaTransactionData, SafeTransactionData},
+    },
+    safety::AllowedHazards,
+    tx::format_amount,
+    SafeClient, SigningClient,
+};
+use serde::Serialize;
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+/// Safe Transaction Service command-line client
+#[derive(Parser)]
+#[command(name = "safe", version)]
+struct Cli {
+    /// Chain ID of the Safe's network
+    #[arg(long, env = "SAFE_CHAIN_ID", default_value_t = 1, global = true)]
+    chain_id: u64,
+    /// Address of the Safe
+    #[arg(long, env = "SAFE_ADDRESS", global = true)]
+    safe: Option<Address>,
+    /// Output JSON, for scripting
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(flatten)]
+    key: KeyArgs,
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// Local signer. Needed by commands that sign or send transactions
+#[derive(Args)]
+struct KeyArgs {
+    /// Hex private key
+    #[arg(
+        long,
+        env = "SAFE_PRIVATE_KEY",
+        hide_env_values = true,
+        global = true,
+        conflicts_with = "keystore"
+    )]
+    private_key: Option<String>,
+    /// Path to an encrypted JSON keystore
+    #[arg(long, env = "SAFE_KEYSTORE", global = true)]
+    keystore: Option<PathBuf>,
+    /// Keystore password
+    #[arg(
+        long,
+        env = "SAFE_KEYSTORE_PASSWORD",
+        hide_env_values = true,
+        global = true
+    )]
+    password: Option<String>,
+}
+
+/// A transaction, from a JSON file or from flags
+#[derive(Args)]
+struct TxArgs {
+    /// JSON file holding a transaction (`to`, `value`, `data`, `operation`),
+    /// optionally with gas settings and `nonce`, or a Transaction Builder
+    /// batch
+    #[arg(long, conflicts_with_all = ["to", "value", "data", "delegatecall"])]
+    file: Option<PathBuf>,
+    /// Target address
+    #[arg(long, required_unless_present = "file")]
+    to: Option<Address>,
+    /// Native asset value, in wei
+    #[arg(long, value_parser = parse_wei)]
+    value: Option<U256>,
+    /// Hex calldata
+    #[arg(long)]
+    data: Option<Bytes>,
+    /// Use DELEGATECALL instead of CALL
+    #[arg(long)]
+    delegatecall: bool,
+    /// Safe nonce. Defaults to the file's nonce, or the next nonce
+    #[arg(long)]
+    nonce: Option<u64>,
+    /// MultiSendCallOnly executing a batch file. Defaults to the deployment
+    /// matching the Safe's singleton
+    #[arg(long, requires = "file")]
+    multisend: Option<Address>,
+}
+
+/// Hazards to allow signing. See `safe_sdk::safety`
+#[derive(Args)]
+struct AllowArgs {
+    /// Allow delegatecalls to contracts other than MultiSend
+    #[arg(long)]
+    allow_delegatecall: bool,
+    /// Allow gas refunds in tokens or to third parties
+    #[arg(long)]
+    allow_gas_refund: bool,
+    /// Allow changes to owners, threshold, modules, guard, fallback handler
+    /// or singleton
+    #[arg(long)]
+    allow_safe_config_change: bool,
+}
+
+impl From<&AllowArgs> for AllowedHazards {
+    fn from(args: &AllowArgs) -> Self {
+        AllowedHazards {
+            delegatecall: args.allow_delegatecall,
+            gas_refund: args.allow_gas_refund,
+            safe_config_change: args.allow_safe_config_change,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Show the Safe's owners, threshold, nonce & modules
+    Info,
+    /// List multisig transactions, most recent first
+    History {
+        /// Maximum number of transactions
+        #[arg(long, default_value_t = 20)]
+        limit: u32,
+        /// Only transactions with this nonce
+        #[arg(long)]
+        nonce: Option<u64>,
+    },
+    /// List pending transactions, grouped by nonce
+    Queue,
+    /// List the Safe's balances
+    Balances,
+    /// List tokens known to the service
+    Tokens {
+        /// Only tokens with this symbol
+        #[arg(long)]
+        symbol: Option<String>,
+        /// Maximum number of tokens
+        #[arg(long, default_value_t = 20)]
+        limit: u64,
+    },
+    /// Sign & propose a transaction
+    Propose {
+        #[command(flatten)]
+        tx: TxArgs,
+        #[command(flatten)]
+        allow: AllowArgs,
+        /// Preview only, without signing
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Sign & submit a confirmation of a proposed transaction
+    Confirm {
+        /// The safe tx hash
+        safe_tx_hash: H256,
+        #[command(flatten)]
+        allow: AllowArgs,
+    },
+    /// Execute a fully confirmed transaction on-chain. The signer pays gas
+    Execute {
+        /// The safe tx hash
+        safe_tx_hash: H256,
+        /// JSON-RPC endpoint
+        #[arg(long, env = "ETH_RPC_URL")]
+        rpc_url: String,
+    },
+    /// Sign & propose a rejection of all proposals at a nonce
+    Reject {
+        /// The nonce
+        nonce: u64,
+    },
+    /// Estimate the `safeTxGas` of a transaction
+    Estimate {
+        #[command(flatten)]
+        tx: TxArgs,
+        /// Estimate via this JSON-RPC endpoint instead of the service
+        #[arg(long, env = "ETH_RPC_URL")]
+        rpc_url: Option<String>,
+    },
+    /// Compute the safe tx hash & preview a transaction, without signing
+    Hash {
+        #[command(flatten)]
+        tx: TxArgs,
+    },
+    /// Check a proposed transaction's hash & confirmation signatures
+    Verify {
+        /// The safe tx hash
+        safe_tx_hash: H256,
+    },
+}
+
+fn parse_wei(value: &str) -> Result<U256, String> {
+    U256::from_dec_str(value).map_err(|e| format!("invalid wei amount: {e}"))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TxSummary {
+    nonce: u64,
+    safe_tx_hash: H256,
+    to: Address,
+    /// Wei, as a decimal string
+    value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    method: Option<String>,
+    confirmations: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    confirmations_required: Option<u32>,
+    is_executed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    is_successful: Option<bool>,
+}
+
+impl TxSummary {
+    fn new(decoder: &Decoder, tx: &MsigTxResponse) -> Self {
+        Self {
+            nonce: tx.nonce,
+            safe_tx_hash: tx.safe_tx_hash,
+            to: tx.to,
+            value: tx.value.to_string(),
+            method: decoder.decode_tx(tx).map(|d| d.method),
+            confirmations: tx.confirmations.len(),
+            confirmations_required: tx.confirmations_required,
+            is_executed: tx.is_executed,
+            is_successful: tx.is_successful,
+        }
+    }
+
+    fn line(&self) -> String {
+        let status = match (self.is_executed, self.is_successful) {
+            (true, Some(false)) => "failed".to_owned(),
+            (true, _) => "executed".to_owned(),
+            (false, _) => match self.confirmations_required {
+                Some(required) => format!("{}/{required} confirmed", self.confirmations),
+                None => format!("{} confirmed", self.confirmations),
+            },
+        };
+        format!(
+            "{:>5}  {:?}  {}  {:<20}  {status}",
+            self.nonce,
+            self.safe_tx_hash,
+            ethers::utils::to_checksum(&self.to, None),
+            self.method.as_deref().unwrap_or("-"),
+        )
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct QueuedSummary {
+    #[serde(flatten)]
+    tx: TxSummary,
+    missing_confirmations: u32,
+    is_rejection: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BalanceSummary {
+    token: Option<Address>,
+    symbol: String,
+    /// Base units, as a decimal string
+    balance: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    formatted: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenSummary {
+    address: String,
+    symbol: String,
+    name: String,
+    decimals: Option<u32>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SignatureCheck {
+    owner: Address,
+    is_owner: bool,
+    /// `None` for contract & pre-approved hash signatures, which are not
+    /// checked
+    valid: Option<bool>,
+}
+
+struct Context {
+    chain_id: u64,
+    safe: Option<Address>,
+    json: bool,
+    key: KeyArgs,
+    client: SafeClient,
+}
+
+impl Context {
+    fn safe(&self) -> CliResult<Address> {
+        self.safe
+            .ok_or_else(|| "missing Safe address: pass --safe or set SAFE_ADDRESS".into())
+    }
+
+    fn wallet(&self) -> CliResult<LocalWallet> {
+        let wallet = match (&self.key.private_key, &self.key.keystore) {
+            (Some(key), _) => key.trim_start_matches("0x").parse::<LocalWallet>()?,
+            (None, Some(path)) => {
+                let password = self.key.password.as_deref().ok_or(
+                    "missing keystore password: pass --password or set SAFE_KEYSTORE_PASSWORD",
+                )?;
+                LocalWallet::decrypt_keystore(path, password)?
+            }
+            (None, None) => {
+                return Err("missing signer: pass --private-key or --keystore".into());
+            }
+        };
+        Ok(wallet.with_chain_id(self.chain_id))
+    }
+
+    fn signing_client(&self, allowed: AllowedHazards) -> CliResult<SigningClient<LocalWallet>> {
+        Ok(
+            SigningClient::with_service_and_signer(self.client.network(), self.wallet()?)
+                .allow_hazards(allowed),
+        )
+    }
+
+    async fn previewer(&self, safe: Address) -> Previewer {
+        // token amounts are formatted only if balances are available
+        let previewer = match self.client.previewer(safe).await {
+            Ok(previewer) => previewer,
+            Err(e) => {
+                eprintln!("warning: could not fetch balances: {e}");
+                Previewer::default().with_label(safe, "this Safe")
+            }
+        };
+        match self.wallet() {
+            Ok(wallet) => previewer.with_signer(wallet.address()),
+            Err(_) => previewer,
+        }
+    }
+
+    /// Print JSON, or text
+    fn emit<T: Serialize>(&self, value: &T, text: impl FnOnce() -> String) -> CliResult<()> {
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(value)?);
+        } else {
+            println!("{}", text().trim_end());
+        }
+        Ok(())
+    }
+
+    async fn safe_tx(&self, safe: Address, args: &TxArgs) -> CliResult<SafeTransactionData> {
+        let (core, gas, nonce) = match &args.file {
+            Some(path) => {
+                let json: serde_json::Value =
+                    serde_json::from_str(&std::fs::read_to_string(path)?)?;
+                // batches list `transactions`, and only full Safe txns carry
+                // a `nonce`
+                if json.get("transactions").is_some() {
+                    let batch: BatchFile = serde_json::from_value(json)?;
+                    let multisend = match args.multisend {
+                        Some(multisend) => multisend,
+                        None => {
+                            let singleton = self.client.safe_info(safe).await?.master_copy;
+                            multisend_call_only(singleton).ok_or_else(|| {
+                                format!(
+                                    "no known MultiSendCallOnly for singleton {singleton:?}, pass --multisend"
+                                )
+                            })?
+                        }
+                    };
+                    (batch.to_multisend(multisend)?, Default::default(), None)
+                } else if json.get("nonce").is_some() {
+                    let tx: SafeTransactionData = serde_json::from_value(json)?;
+                    (tx.core, tx.gas, Some(tx.nonce))
+                } else {
+                    (serde_json::from_value(json)?, Default::default(), None)
+                }
+            }
+            None => {
+                let core = MetaTransactionData {
+                    to: args.to.expect("required by clap").into(),
+                    value: args.value.unwrap_or_default(),
+                    data: args.data.clone(),
+                    operation: Some(if args.delegatecall {
+                        Operations::DelegateCall
+                    } else {
+                        Operations::Call
+                    }),
+                };
+                (core, Default::default(), None)
+            }
+        };
+        let nonce = match args.nonce.or(nonce) {
+            Some(nonce) => nonce,
+            None => self.client.next_nonce(safe).await?,
+        };
+        Ok(SafeTransactionData { core, gas, nonce })
+    }
+}
+
+/// Recover the signer of a confirmation of `safe_tx_hash`. `None` for
+/// signature types that cannot be checked offline
+fn recover(safe_tx_hash: H256, signature: &str) -> Option<Result<Address, ()>> {
+    let bytes: Bytes = match signature.parse() {
+        Ok(bytes) => bytes,
+        Err(_) => return Some(Err(())),
+    };
+    let mut signature = match Signature::try_from(bytes.as_ref()) {
+        Ok(signature) => signature,
+        Err(_) => return Some(Err(())),
+    };
+    match signature.v {
+        27 | 28 => Some(signature.recover(safe_tx_hash).map_err(|_| ())),
+        // eth_sign signatures over the hash
+        31 | 32 => {
+            signature.v -= 4;
+            Some(signature.recover(safe_tx_hash.as_bytes()).map_err(|_| ()))
+        }
+        // contract signatures & pre-approved hashes
+        _ => None,
+    }
+}
+
+async fn run(cli: Cli) -> CliResult<()> {
+    let service = TxService::by_chain_id(cli.chain_id)
+        .ok_or_else(|| format!("no known service for chain ID {}", cli.chain_id))?;
+    let ctx = Context {
+        chain_id: cli.chain_id,
+        safe: cli.safe,
+        json: cli.json,
+        key: cli.key,
+        client: SafeClient::new(service),
+    };
+    let decoder = Decoder::default();
+
+    match cli.command {
+        Command::Info => {
+            let info = ctx.client.safe_info(ctx.safe()?).await?;
+            ctx.emit(&info, || {
+                let mut out = format!(
+                    "Safe:      {}\nVersion:   {}\nNonce:     {}\nThreshold: {}/{}\n",
+                    ethers::utils::to_checksum(&info.safe_address, None),
+                    info.version.as_deref().unwrap_or("unknown"),
+                    info.nonce,
+                    info.threshold,
+                    info.owners.len(),
+                );
+                for owner in &info.owners {
+                    out += &format!("  owner   {}\n", ethers::utils::to_checksum(owner, None));
+                }
+                for module in &info.modules {
+                    out += &format!("  module  {module}\n");
+                }
+                if !info.guard.is_zero() {
+                    out += &format!("Guard:     {:?}\n", info.guard);
+                }
+                out
+            })
+        }
+        Command::History { limit, nonce } => {
+            let mut filters = ctx.client.msig_history_builder().limit(limit);
+            if let Some(nonce) = nonce {
+                filters = filters.nonce(nonce);
+            }
+            let txns: Vec<_> = filters
+                .query(ctx.safe()?)
+                .await?
+                .results
+                .iter()
+                .map(|tx| TxSummary::new(&decoder, tx))
+                .collect();
+            ctx.emit(&txns, || {
+                txns.iter()
+                    .map(TxSummary::line)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+        }
+        Command::Queue => {
+            let queue = ctx.client.queue(ctx.safe()?).await?;
+            let txns: Vec<_> = queue
+                .nonces
+                .iter()
+                .flat_map(|n| n.transactions.iter())
+                .map(|pending| QueuedSummary {
+                    tx: TxSummary::new(&decoder, &pending.tx),
+                    missing_confirmations: pending.missing_confirmations,
+                    is_rejection: pending.tx.is_rejection(),
+                })
+                .collect();
+            ctx.emit(&txns, || {
+                let mut out = format!(
+                    "On-chain nonce {}, threshold {}/{}\n",
+                    queue.nonce,
+                    queue.threshold,
+                    queue.owners.len()
+                );
+                for queued in &txns {
+                    out += &queued.tx.line();
+                    if queued.is_rejection {
+                        out += "  (rejection)";
+                    }
+                    out.push('\n');
+                }
+                for conflict in queue.conflicts() {
+                    out += &format!(
+                        "WARNING: {} conflicting transactions at nonce {}\n",
+                        conflict.transactions.len(),
+                        conflict.nonce
+                    );
+                }
+                out
+            })
+        }
+        Command::Balances => {
+            let balances: Vec<_> = ctx
+                .client
+                .balances(ctx.safe()?)
+                .await?
+                .iter()
+                .map(|b| BalanceSummary {
+                    token: b.token_address,
+                    symbol: b
+                        .token
+                        .as_ref()
+                        .map_or_else(|| "native".to_owned(), |t| t.symbol.clone()),
+                    balance: b.balance.to_string(),
+                    formatted: b.decimals().map(|d| format_amount(b.balance, d)),
+                })
+                .collect();
+            ctx.emit(&balances, || {
+                balances
+                    .iter()
+                    .map(|b| {
+                        format!(
+                            "{:<10} {:>30}  {}",
+                            b.symbol,
+                            b.formatted.as_ref().unwrap_or(&b.balance),
+                            b.token.map(|t| format!("{t:?}")).unwrap_or_default()
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+        }
+        Command::Tokens { symbol, limit } => {
+            let mut filters = ctx.client.tokens_builder().limit(limit);
+            if let Some(symbol) = symbol {
+                filters = filters.symbol(symbol);
+            }
+            let tokens: Vec<_> = filters
+                .query()
+                .await?
+                .results
+                .into_iter()
+                .map(|t| TokenSummary {
+                    address: t.address,
+                    symbol: t.symbol,
+                    name: t.name,
+                    decimals: t.decimals,
+                })
+                .collect();
+            ctx.emit(&tokens, || {
+                tokens
+                    .iter()
+                    .map(|t| format!("{:<10} {}  {}", t.symbol, t.address, t.name))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+        }
+        Command::Propose { tx, allow, dry_run } => {
+            let safe = ctx.safe()?;
+            let tx = ctx.safe_tx(safe, &tx).await?;
+            let preview = ctx.previewer(safe).await.preview(safe, ctx.chain_id, &tx);
+            if dry_run {
+                return ctx.emit(&preview, || preview.to_string());
+            }
+            if !ctx.json {
+                eprintln!("{preview}");
+            }
+            let proposed = ctx
+                .signing_client((&allow).into())?
+                .propose_tx(tx, safe)
+                .await?;
+            let summary = TxSummary::new(&decoder, &proposed);
+            ctx.emit(&summary, || format!("Proposed {:?}", summary.safe_tx_hash))
+        }
+        Command::Confirm {
+            safe_tx_hash,
+            allow,
+        } => {
+            let tx = ctx.client.transaction_info(safe_tx_hash).await?;
+            if !ctx.json {
+                let preview = ctx.previewer(tx.safe).await.preview_msig(ctx.chain_id, &tx);
+                eprintln!("{preview}");
+            }
+            let confirmed = ctx
+                .signing_client((&allow).into())?
+                .confirm_tx(safe_tx_hash)
+                .await?;
+            let summary = TxSummary::new(&decoder, &confirmed);
+            ctx.emit(&summary, || summary.line())
+        }
+        Command::Execute {
+            safe_tx_hash,
+            rpc_url,
+        } => {
+            let tx = ctx.client.transaction_info(safe_tx_hash).await?;
+            if tx.is_executed {
+                return Err(format!("{safe_tx_hash:?} is already executed").into());
+            }
+            let data = SafeTransactionData::from(&tx);
+            if data.safe_tx_hash(tx.safe, ctx.chain_id) != safe_tx_hash {
+                return Err("service transaction does not match its safe tx hash".into());
+            }
+            let info = ctx.client.safe_info(tx.safe).await?;
+            let pending = ctx
+                .client
+                .queue(tx.safe)
+                .await?
+                .at_nonce(tx.nonce)
+                .and_then(|n| {
+                    n.transactions
+                        .iter()
+                        .find(|t| t.tx.safe_tx_hash == safe_tx_hash)
+                        .cloned()
+                })
+                .ok_or("transaction is not pending at the Safe's current nonce or later")?;
+            if !pending.is_executable() {
+                return Err(
+                    format!("missing {} confirmation(s)", pending.missing_confirmations).into(),
+                );
+            }
+            let signatures = tx.packed_signatures(&info.owners, info.threshold)?;
+
+            let provider = Provider::<Http>::try_from(rpc_url.as_str())?;
+            let provider = Arc::new(SignerMiddleware::new(provider, ctx.wallet()?));
+            let request = TransactionRequest::new()
+                .to(tx.safe)
+                .data(exec_transaction_calldata(&data, signatures));
+            let receipt = provider
+                .send_transaction(request, None)
+                .await?
+                .await?
+                .ok_or("transaction dropped")?;
+            let success = execution_success(&receipt, tx.safe, safe_tx_hash).unwrap_or(false);
+
+            #[derive(Serialize)]
+            #[serde(rename_all = "camelCase")]
+            struct Executed {
+                transaction_hash: H256,
+                success: bool,
+            }
+            let executed = Executed {
+                transaction_hash: receipt.transaction_hash,
+                success,
+            };
+            ctx.emit(&executed, || {
+                format!(
+                    "Executed in {:?}: {}",
+                    executed.transaction_hash,
+                    if success { "success" } else { "FAILED" }
+                )
+            })?;
+            if !success {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Command::Reject { nonce } => {
+            let rejection = ctx
+                .signing_client(Default::default())?
+                .propose_rejection(ctx.safe()?, nonce)
+                .await?;
+            let rejected: Vec<_> = rejection
+                .replaces
+                .iter()
+                .map(|tx| TxSummary::new(&decoder, tx))
+                .collect();
+            ctx.emit(
+                &serde_json::json!({
+                    "rejection": TxSummary::new(&decoder, &rejection.rejection),
+                    "replaces": rejected,
+                }),
+                || {
+                    let mut out =
+                        format!("Proposed rejection {:?}", rejection.rejection.safe_tx_hash);
+                    for tx in &rejected {
+                        out += &format!("\n  replaces {}", tx.line());
+                    }
+                    out
+                },
+            )
+        }
+        Command::Estimate { tx, rpc_url } => {
+            let safe = ctx.safe()?;
+            let tx = ctx.safe_tx(safe, &tx).await?;
+            let safe_tx_gas = match rpc_url {
+                Some(url) => {
+                    let provider = Provider::<Http>::try_from(url.as_str())?;
+                    estimate_safe_tx_gas(&provider, safe, &tx.core)
+                        .await?
+                        .into()
+                }
+                None => ctx.client.estimate_gas(safe, &tx.core).await?,
+            };
+            ctx.emit(
+                &serde_json::json!({ "safeTxGas": safe_tx_gas.to_string() }),
+                || format!("safeTxGas: {safe_tx_gas}"),
+            )
+        }
+        Command::Hash { tx } => {
+            let safe = ctx.safe()?;
+            let tx = ctx.safe_tx(safe, &tx).await?;
+            let preview = ctx.previewer(safe).await.preview(safe, ctx.chain_id, &tx);
+            ctx.emit(&preview, || preview.to_string())
+        }
+        Command::Verify { safe_tx_hash } => {
+            let tx = ctx.client.transaction_info(safe_tx_hash).await?;
+            let info = ctx.client.safe_info(tx.safe).await?;
+            let preview = ctx.previewer(tx.safe).await.preview_msig(ctx.chain_id, &tx);
+            let hash_matches = preview.safe_tx_hash == safe_tx_hash && !preview.hash_mismatch();
+            let signatures: Vec<_> = tx
+                .confirmations
+                .iter()
+                .map(|c| SignatureCheck {
+                    owner: c.owner,
+                    is_owner: info.owners.contains(&c.owner),
+                    valid: recover(safe_tx_hash, &c.signature).map(|r| r == Ok(c.owner)),
+                })
+                .collect();
+            let ok = hash_matches
+                && signatures
+                    .iter()
+                    .all(|s| s.is_owner && s.valid != Some(false));
+
+            ctx.emit(
+                &serde_json::json!({
+                    "ok": ok,
+                    "hashMatches": hash_matches,
+                    "signatures": signatures,
+                    "preview": preview,
+                }),
+                || {
+                    let mut out = preview.to_string();
+                    out += &format!(
+                        "Hash:         {}\n",
+                        if hash_matches { "ok" } else { "MISMATCH" }
+                    );
+                    for s in &signatures {
+                        let status = match (s.is_owner, s.valid) {
+                            (false, _) => "NOT AN OWNER",
+                            (_, Some(false)) => "INVALID",
+                            (_, Some(true)) => "ok",
+                            (_, None) => "not checked",
+                        };
+                        out += &format!(
+                            "Signature:    {} {status}\n",
+                            ethers::utils::to_checksum(&s.owner, None)
+                        );
+                    }
+                    out
+                },
+            )?;
+            if !ok {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    if let Err(e) = run(Cli::parse()).await {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+}
@@ -1,23 +1,40 @@
-use std::ops::Deref;
+use std::{ops::Deref, sync::Arc};
 
 use ethers::{
     signers::Signer,
-    types::{Address, H256, U256},
+    types::{transaction::eip712::Eip712Error, Address, Bytes, Signature, H256, U256},
 };
 use reqwest::{StatusCode, Url};
+use tokio_stream::StreamExt;
 
 use crate::{
-    json_get, json_post,
+    json_delete, json_get, json_post,
     networks::{self, TxService},
+    policy::{PolicyEngine, SpendRecord},
     rpc::{
         balances::{BalancesFilters, BalancesRequest, BalancesResponse},
         common::ErrorResponse,
+        confirm::ConfirmationRequest,
+        decoder::{DataDecoderRequest, DataDecoderResponse},
+        delegates::{
+            AddDelegateRequest, DelegateResponse, DelegatesFilters, DelegatesRequest,
+            DelegatesResponse, RemoveDelegateRequest,
+        },
         estimate::{EstimateRequest, EstimateResponse},
         info::{SafeInfoRequest, SafeInfoResponse},
+        messages::{
+            CreateMessageRequest, MessageRequest, MessageSignatureRequest, SafeMessage,
+            SafeMessageResponse, SafeMessagesRequest, SafeMessagesResponse,
+        },
         msig_history::{MsigHistoryFilters, MsigHistoryResponse, MsigTxRequest, MsigTxResponse},
-        propose::{MetaTransactionData, ProposeRequest, SafeTransactionData},
+        propose::{
+            DeleteProposalRequest, MetaTransactionData, ProposalOrigin, ProposeRequest,
+            RejectionProposal, SafeTransactionData,
+        },
+        queue::PendingQueue,
         tokens::{TokenInfoFilters, TokenInfoRequest, TokenInfoResponse},
     },
+    safety::{self, AllowedHazards},
 };
 
 /// Gnosis Client Errors
@@ -49,6 +66,74 @@ pub enum ClientError {
     /// API Error
     #[error("API usage error: {0}")]
     ApiError(ErrorResponse),
+    /// Proposal sender is neither an owner nor a delegate of the Safe
+    #[error("{sender:?} is neither an owner nor a delegate of Safe {safe:?}")]
+    UnauthorizedProposer {
+        /// The Safe the proposal was submitted to
+        safe: Address,
+        /// The sender of the proposal
+        sender: Address,
+    },
+    /// Proposal is not known to the service
+    #[error("Proposal {0:?} not found")]
+    ProposalNotFound(H256),
+    /// Proposal has already been executed, and may not be deleted
+    #[error("Proposal {0:?} has already been executed")]
+    ProposalAlreadyExecuted(H256),
+    /// Only the proposer of a transaction may delete it
+    #[error(
+        "Proposal {safe_tx_hash:?} was not proposed by signer {signer:?}. Proposer: {proposer:?}"
+    )]
+    NotProposer {
+        /// The safe tx hash of the proposal
+        safe_tx_hash: H256,
+        /// The original proposer. `None` if the service does not report one
+        proposer: Option<Address>,
+        /// The address of the signer attempting deletion
+        signer: Address,
+    },
+    /// The safe tx hash computed locally does not match the expected hash
+    #[error("Safe tx hash mismatch. Expected {expected:?}, computed {computed:?}")]
+    SafeTxHashMismatch {
+        /// The expected hash
+        expected: H256,
+        /// The hash computed from the transaction details
+        computed: H256,
+    },
+    /// Offline bundle error
+    #[error("{0}")]
+    Bundle(#[from] crate::offline::BundleError),
+    /// Transaction conversion error
+    #[error("{0}")]
+    Conversion(#[from] crate::rpc::propose::ConversionError),
+    /// The transaction was rejected by the signing client's policy
+    #[error("{0}")]
+    PolicyViolation(#[from] crate::policy::PolicyViolations),
+    /// The transaction has hazards the signing client does not allow
+    #[error("{0}")]
+    UnsafeTransaction(#[from] crate::safety::UnsafeTransaction),
+    /// A Safe message could not be hashed
+    #[error("{0}")]
+    TypedData(#[from] Eip712Error),
+    /// The Safe message hash computed locally does not match the expected hash
+    #[error("Safe message hash mismatch. Expected {expected:?}, computed {computed:?}")]
+    MessageHashMismatch {
+        /// The expected hash
+        expected: H256,
+        /// The hash computed from the message
+        computed: H256,
+    },
+    /// A watcher timed out
+    #[error("Timed out after {0:?}")]
+    Timeout(std::time::Duration),
+    /// Another transaction at the same nonce was executed
+    #[error("Transaction {safe_tx_hash:?} was replaced by {by:?}")]
+    Replaced {
+        /// Safe tx hash of the replaced transaction
+        safe_tx_hash: H256,
+        /// Safe tx hash of the executed transaction
+        by: H256,
+    },
     /// No known service endpoint for chain_id
     #[error("No known service URL for chain id {0}. Hint: if using a custom tx service api, specify via a `TxService` object, rather than via a chain id.")]
     UnknownServiceId(u64),
@@ -155,6 +240,9 @@ impl SafeClient {
         SigningClient {
             client: self,
             signer,
+            policy: None,
+            allowed_hazards: Default::default(),
+            origin: None,
         }
     }
 
@@ -275,6 +363,20 @@ impl SafeClient {
         MsigHistoryFilters::new(self)
     }
 
+    /// Get the queue of pending transactions: all unexecuted transactions
+    /// with nonce at or above the on-chain nonce, grouped by nonce
+    #[tracing::instrument(skip(self))]
+    pub async fn queue(&self, safe_address: Address) -> ClientResult<PendingQueue> {
+        let info = self.safe_info(safe_address).await?;
+        let txns = self
+            .msig_history_builder()
+            .min_nonce(info.nonce)
+            .into_stream(safe_address)
+            .collect::<ClientResult<Vec<_>>>()
+            .await?;
+        Ok(PendingQueue::new(&info, txns))
+    }
+
     /// Estimate the safeTxGas to attach to a transaction proposal
     #[tracing::instrument(skip(self, tx))]
     pub async fn estimate_gas<'a>(
@@ -291,6 +393,221 @@ impl SafeClient {
         .map(|resp: Option<EstimateResponse>| resp.unwrap().into())
     }
 
+    /// Decode calldata via the API, e.g. to preview a proposal's decoding
+    /// before proposing it. `to` helps the API select the target's ABI.
+    /// Errors if the API cannot decode the data
+    #[tracing::instrument(skip(self, data))]
+    pub async fn decode_data(
+        &self,
+        data: Bytes,
+        to: Option<Address>,
+    ) -> ClientResult<DataDecoderResponse> {
+        let req = DataDecoderRequest::new(data, to);
+        json_post!(self.client, DataDecoderRequest::url(self.url()), &req)?
+            .ok_or_else(|| ClientError::Other("Data could not be decoded".to_owned()))
+    }
+
+    /// Decode the data of a transaction via the API. See
+    /// [`SafeClient::decode_data`]
+    pub async fn decode_tx(&self, tx: &MetaTransactionData) -> ClientResult<DataDecoderResponse> {
+        let req = DataDecoderRequest::from(tx);
+        self.decode_data(req.data, req.to).await
+    }
+
+    /// Get the delegates registered for a Safe
+    #[tracing::instrument(skip(self))]
+    pub async fn delegates(&self, safe_address: Address) -> ClientResult<DelegatesResponse> {
+        self.delegates_builder().safe(safe_address).query().await
+    }
+
+    /// Get a filtered list of delegates from the API
+    #[tracing::instrument(skip(self, filters))]
+    pub(crate) async fn filtered_delegates(
+        &self,
+        filters: impl IntoIterator<Item = (&'static str, String)>,
+    ) -> ClientResult<DelegatesResponse> {
+        json_get!(
+            &self.client,
+            DelegatesRequest::url(self.url()),
+            DelegatesResponse,
+            filters,
+        )
+        .map(Option::unwrap)
+    }
+
+    /// Create a filter builder for delegates
+    #[tracing::instrument(skip(self))]
+    pub fn delegates_builder(&self) -> DelegatesFilters<'_> {
+        DelegatesFilters::new(self)
+    }
+
+    /// True if `address` may propose transactions to the Safe as a delegate.
+    ///
+    /// This is the case if it was registered for this Safe specifically, or
+    /// for all Safes of a delegator that is a current owner
+    #[tracing::instrument(skip(self))]
+    pub async fn is_delegate(&self, safe_address: Address, address: Address) -> ClientResult<bool> {
+        self.is_delegate_of(safe_address, address, None).await
+    }
+
+    /// As [`SafeClient::is_delegate`], reusing the Safe's `owners` if
+    /// already known
+    async fn is_delegate_of(
+        &self,
+        safe_address: Address,
+        address: Address,
+        owners: Option<&[Address]>,
+    ) -> ClientResult<bool> {
+        let delegates: Vec<_> = self
+            .delegates_builder()
+            .delegate(address)
+            .query()
+            .await?
+            .results
+            .into_iter()
+            .filter(|d| d.delegate == address)
+            .collect();
+
+        if delegates.iter().any(|d| d.safe == Some(safe_address)) {
+            return Ok(true);
+        }
+        if delegates.iter().all(|d| d.safe.is_some()) {
+            return Ok(false);
+        }
+
+        // delegations without a safe apply to all safes of the delegator
+        let fetched;
+        let owners = match owners {
+            Some(owners) => owners,
+            None => {
+                fetched = self.safe_info(safe_address).await?.owners;
+                &fetched
+            }
+        };
+        Ok(delegates
+            .iter()
+            .any(|d| d.safe.is_none() && owners.contains(&d.delegator)))
+    }
+
+    /// Submit a signed proposal request for storage on the API. The signer
+    /// is not required, so proposals signed elsewhere may be submitted.
+    ///
+    /// The service rejects senders that are neither owners nor delegates.
+    /// See [`SafeClient::submit_signed_proposal_with_owners`] to check
+    /// before submitting
+    #[tracing::instrument(skip(self, proposal))]
+    pub async fn submit_signed_proposal(
+        &self,
+        proposal: ProposeRequest,
+        safe_address: Address,
+    ) -> ClientResult<MsigTxResponse> {
+        let tx_hash = proposal.safe_tx_hash();
+        // little crufty. TODO: fix macro more gooder
+        json_post!(
+            self.client,
+            ProposeRequest::url(self.url(), safe_address),
+            &proposal
+        )
+        .map(|_: Option<()>| ())?;
+        self.transaction_info(tx_hash).await
+    }
+
+    /// Submit a signed proposal request, given the Safe's current `owners`.
+    /// Errors with [`ClientError::UnauthorizedProposer`] if the sender is
+    /// neither an owner nor a delegate registered by an owner. Delegates are
+    /// only looked up if the sender is not an owner
+    #[tracing::instrument(skip(self, proposal, owners))]
+    pub async fn submit_signed_proposal_with_owners(
+        &self,
+        proposal: ProposeRequest,
+        safe_address: Address,
+        owners: &[Address],
+    ) -> ClientResult<MsigTxResponse> {
+        let sender: Address = proposal.signature().sender().into();
+        if !owners.contains(&sender)
+            && !self
+                .is_delegate_of(safe_address, sender, Some(owners))
+                .await?
+        {
+            return Err(ClientError::UnauthorizedProposer {
+                safe: safe_address,
+                sender,
+            });
+        }
+        self.submit_signed_proposal(proposal, safe_address).await
+    }
+
+    /// Submit an owner's signature of a proposed transaction. The signer is
+    /// not required, so signatures produced elsewhere may be submitted
+    #[tracing::instrument(skip(self))]
+    pub async fn submit_confirmation(
+        &self,
+        safe_tx_hash: H256,
+        signature: Signature,
+    ) -> ClientResult<()> {
+        json_post!(
+            self.client,
+            ConfirmationRequest::url(self.url(), safe_tx_hash),
+            &ConfirmationRequest::from(signature)
+        )
+        .map(|_: Option<serde_json::Value>| ())
+    }
+
+    /// Get the off-chain messages of a Safe
+    #[tracing::instrument(skip(self))]
+    pub async fn messages(&self, safe_address: Address) -> ClientResult<SafeMessagesResponse> {
+        json_get!(
+            &self.client,
+            SafeMessagesRequest::url(self.url(), safe_address),
+            SafeMessagesResponse
+        )
+        .map(Option::unwrap)
+    }
+
+    /// Get a Safe message & its signatures by Safe message hash. Errors on
+    /// unknown message
+    #[tracing::instrument(skip(self))]
+    pub async fn message(&self, message_hash: H256) -> ClientResult<SafeMessageResponse> {
+        json_get!(
+            &self.client,
+            MessageRequest::url(self.url(), message_hash),
+            SafeMessageResponse
+        )
+        .map(Option::unwrap)
+    }
+
+    /// Submit a new Safe message, signed by an owner. The signer is not
+    /// required, so messages signed elsewhere may be submitted
+    #[tracing::instrument(skip(self, request))]
+    pub async fn submit_message(
+        &self,
+        safe_address: Address,
+        request: &CreateMessageRequest,
+    ) -> ClientResult<()> {
+        json_post!(
+            self.client,
+            CreateMessageRequest::url(self.url(), safe_address),
+            request
+        )
+        .map(|_: Option<serde_json::Value>| ())
+    }
+
+    /// Submit an owner's signature of a Safe message. The signer is not
+    /// required, so signatures produced elsewhere may be submitted
+    #[tracing::instrument(skip(self))]
+    pub async fn submit_message_signature(
+        &self,
+        message_hash: H256,
+        signature: Signature,
+    ) -> ClientResult<()> {
+        json_post!(
+            self.client,
+            MessageSignatureRequest::url(self.url(), message_hash),
+            &MessageSignatureRequest::from(signature)
+        )
+        .map(|_: Option<serde_json::Value>| ())
+    }
+
     /// Get the details of a transaction. Errors on unknown transaction
     #[tracing::instrument(skip(self))]
     pub async fn transaction_info(&self, tx_hash: H256) -> ClientResult<MsigTxResponse> {
@@ -309,6 +626,9 @@ impl SafeClient {
 pub struct SigningClient<S> {
     pub(crate) client: SafeClient,
     pub(crate) signer: S,
+    pub(crate) policy: Option<Arc<PolicyEngine>>,
+    pub(crate) allowed_hazards: AllowedHazards,
+    pub(crate) origin: Option<ProposalOrigin>,
 }
 
 impl<S> Deref for SigningClient<S> {
@@ -342,41 +662,186 @@ impl<S: Signer> SigningClient<S> {
         SafeClient::from(service).with_signer(signer)
     }
 
+    /// Enforce a policy on transactions before they are signed by
+    /// [`SigningClient::propose_tx`] and the methods built on it,
+    /// [`SigningClient::confirm_tx`] and [`SigningClient::cosign_bundle`]. The engine
+    /// may be shared between clients, e.g. to share daily limits
+    pub fn with_policy(mut self, policy: Arc<PolicyEngine>) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Return the policy engine, if any
+    pub fn policy(&self) -> Option<&PolicyEngine> {
+        self.policy.as_deref()
+    }
+
+    /// Allow signing transactions with these hazards, e.g. delegatecalls to
+    /// contracts other than MultiSend. By default, transactions with any
+    /// hazard are refused. See [`crate::safety`]
+    pub fn allow_hazards(mut self, allowed: AllowedHazards) -> Self {
+        self.allowed_hazards = allowed;
+        self
+    }
+
+    /// Return the hazards the client allows signing
+    pub fn allowed_hazards(&self) -> AllowedHazards {
+        self.allowed_hazards
+    }
+
+    /// Attach origin metadata to proposals, so co-signers see who or what
+    /// proposed them
+    pub fn with_origin(mut self, origin: ProposalOrigin) -> Self {
+        self.origin = Some(origin);
+        self
+    }
+
+    /// Return the origin metadata attached to proposals, if any
+    pub fn origin(&self) -> Option<&ProposalOrigin> {
+        self.origin.as_ref()
+    }
+
+    /// Attach the client's origin metadata, if any, to a proposal
+    pub(crate) fn attach_origin(&self, proposal: ProposeRequest) -> ProposeRequest {
+        match &self.origin {
+            Some(origin) => proposal.with_origin(origin),
+            None => proposal,
+        }
+    }
+
+    /// Refuse a transaction about to be signed if it has hazards that are not
+    /// allowed
+    pub(crate) fn check_hazards(
+        &self,
+        safe_address: Address,
+        tx: &SafeTransactionData,
+    ) -> ClientResult<()> {
+        Ok(safety::check_hazards(
+            safe_address,
+            self.signer.address(),
+            tx,
+            &self.allowed_hazards,
+        )?)
+    }
+
+    /// Check a transaction about to be signed for hazards, then evaluate
+    /// the policy, if any, recording its transfers toward daily limits. The
+    /// record must be rolled back if the transaction is not submitted
+    pub(crate) fn check_before_signing(
+        &self,
+        safe_address: Address,
+        tx: &SafeTransactionData,
+    ) -> ClientResult<Option<SpendRecord>> {
+        self.check_hazards(safe_address, tx)?;
+        match &self.policy {
+            Some(policy) => Ok(Some(policy.check_and_record(safe_address, tx)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Roll back transfers recorded by the policy, if any
+    pub(crate) fn rollback_spend(&self, record: Option<SpendRecord>) {
+        if let (Some(policy), Some(record)) = (&self.policy, record) {
+            policy.rollback(record);
+        }
+    }
+
     /// Submit a signed proposal request for storage on the API
+    ///
+    /// The proposal sender may be an owner of the Safe, or a delegate
+    /// registered by an owner. The service rejects it otherwise
     pub async fn submit_proposal(
         &self,
         proposal: ProposeRequest,
         safe_address: Address,
     ) -> SigningClientResult<MsigTxResponse, S> {
-        let tx_hash = proposal.safe_tx_hash();
-        // little crufty. TODO: fix macro more gooder
-        json_post!(
-            self.client,
-            ProposeRequest::url(self.url(), safe_address),
-            &proposal
-        )
-        .map(|_: Option<()>| ())?;
-        Ok(self.transaction_info(tx_hash).await?)
+        Ok(self
+            .client
+            .submit_signed_proposal(proposal, safe_address)
+            .await?)
+    }
+
+    /// Submit a signed proposal request, given the Safe's current `owners`.
+    /// Errors with [`ClientError::UnauthorizedProposer`] if the sender is
+    /// neither an owner nor a delegate
+    pub async fn submit_proposal_with_owners(
+        &self,
+        proposal: ProposeRequest,
+        safe_address: Address,
+        owners: &[Address],
+    ) -> SigningClientResult<MsigTxResponse, S> {
+        Ok(self
+            .client
+            .submit_signed_proposal_with_owners(proposal, safe_address, owners)
+            .await?)
+    }
+
+    /// Confirm a proposed transaction, by signing it and submitting the
+    /// signature to the API. Like proposals, confirmations are checked for
+    /// hazards and against the policy, if any. Errors with
+    /// [`ClientError::UnsafeTransaction`] or [`ClientError::PolicyViolation`]
+    /// if refused
+    pub async fn confirm_tx(&self, safe_tx_hash: H256) -> SigningClientResult<MsigTxResponse, S> {
+        let tx = self.transaction_info(safe_tx_hash).await?;
+        let data = SafeTransactionData::from(&tx);
+
+        let computed = data.safe_tx_hash(tx.safe, self.signer.chain_id());
+        if computed != safe_tx_hash {
+            return Err(ClientError::SafeTxHashMismatch {
+                expected: safe_tx_hash,
+                computed,
+            }
+            .into());
+        }
+
+        let record = self.check_before_signing(tx.safe, &data)?;
+        let result = match data
+            .sign(&self.signer, tx.safe, self.signer.chain_id())
+            .await
+        {
+            Ok(signature) => self
+                .submit_confirmation(safe_tx_hash, signature.signature())
+                .await
+                .map_err(Into::into),
+            Err(e) => Err(SigningClientError::SignerError(e)),
+        };
+        if result.is_err() {
+            self.rollback_spend(record);
+        }
+        result?;
+        Ok(self.transaction_info(safe_tx_hash).await?)
     }
 
-    /// Propose a SafeTransaction to the API. First signs the transaction
-    /// request with the signer, then submits
+    /// Propose a SafeTransaction to the API. First checks for hazards and
+    /// the policy, if any, then signs the transaction request with the
+    /// signer, then submits. Errors with [`ClientError::UnsafeTransaction`]
+    /// or [`ClientError::PolicyViolation`] if refused
     pub async fn propose_tx(
         &self,
         tx: SafeTransactionData,
         safe_address: Address,
     ) -> SigningClientResult<MsigTxResponse, S> {
-        let proposal = tx
+        let record = self.check_before_signing(safe_address, &tx)?;
+        let result = match tx
             .into_request(&self.signer, safe_address, self.signer.chain_id())
             .await
-            .map_err(SigningClientError::SignerError)?;
-        self.submit_proposal(proposal, safe_address).await
+        {
+            Ok(proposal) => {
+                self.submit_proposal(self.attach_origin(proposal), safe_address)
+                    .await
+            }
+            Err(e) => Err(SigningClientError::SignerError(e)),
+        };
+        // the limit is only used up by accepted proposals
+        if result.is_err() {
+            self.rollback_spend(record);
+        }
+        result
     }
 
     /// Propose a transaction to the API. Converts to a Safe Transaction, then
-    /// signs, then submits
-    ///
-    /// TODO: more implementations of `From<X> for MetaTransactionData`
+    /// signs, then submits. ethers transactions & abigen contract calls can
+    /// be converted via `MetaTransactionData::try_from`
     pub async fn propose(
         &self,
         tx: impl Into<MetaTransactionData>,
@@ -390,4 +855,338 @@ impl<S: Signer> SigningClient<S> {
         };
         self.propose_tx(proposal, safe_address).await
     }
+
+    /// Propose an off-chain message for the Safe to sign, with the signer's
+    /// signature. Once signed by threshold owners, see
+    /// [`SafeMessageResponse::combined_signature`]
+    pub async fn propose_message(
+        &self,
+        safe_address: Address,
+        message: impl Into<SafeMessage>,
+        safe_app_id: Option<u64>,
+    ) -> SigningClientResult<SafeMessageResponse, S> {
+        let message = message.into();
+        let chain_id = self.signer.chain_id();
+        let signature = SafeMessage::sign(safe_address, chain_id, message.hash()?, &self.signer)
+            .await
+            .map_err(SigningClientError::SignerError)?;
+        let message_hash = message.safe_message_hash(safe_address, chain_id)?;
+
+        let request = CreateMessageRequest {
+            message,
+            safe_app_id,
+            signature,
+        };
+        self.submit_message(safe_address, &request).await?;
+        Ok(self.message(message_hash).await?)
+    }
+
+    /// Sign a proposed Safe message, and submit the signature to the API.
+    /// The message is hashed locally, and must match `message_hash`
+    pub async fn confirm_message(
+        &self,
+        message_hash: H256,
+    ) -> SigningClientResult<SafeMessageResponse, S> {
+        let message = self.message(message_hash).await?;
+        let chain_id = self.signer.chain_id();
+
+        let computed = message.message.safe_message_hash(message.safe, chain_id)?;
+        if computed != message_hash {
+            return Err(ClientError::MessageHashMismatch {
+                expected: message_hash,
+                computed,
+            }
+            .into());
+        }
+
+        let signature = SafeMessage::sign(
+            message.safe,
+            chain_id,
+            message.message.hash()?,
+            &self.signer,
+        )
+        .await
+        .map_err(SigningClientError::SignerError)?;
+        self.submit_message_signature(message_hash, signature)
+            .await?;
+        Ok(self.message(message_hash).await?)
+    }
+
+    /// Register `delegate` as able to propose transactions on behalf of the
+    /// signer. If `safe_address` is `None`, the delegate may propose for every
+    /// Safe the signer owns
+    pub async fn add_delegate(
+        &self,
+        safe_address: Option<Address>,
+        delegate: Address,
+        label: impl Into<String>,
+    ) -> SigningClientResult<(), S> {
+        let req = AddDelegateRequest::sign(&self.signer, safe_address, delegate, label)
+            .await
+            .map_err(SigningClientError::SignerError)?;
+        json_post!(self.client, AddDelegateRequest::url(self.url()), &req)
+            .map(|_: Option<DelegateResponse>| ())?;
+        Ok(())
+    }
+
+    /// Remove a delegate previously registered by the signer
+    pub async fn remove_delegate(&self, delegate: Address) -> SigningClientResult<(), S> {
+        let req = RemoveDelegateRequest::sign(&self.signer, delegate)
+            .await
+            .map_err(SigningClientError::SignerError)?;
+        json_delete!(
+            self.client,
+            RemoveDelegateRequest::url(self.url(), delegate),
+            &req
+        )
+        .map(|_: Option<()>| ())?;
+        Ok(())
+    }
+
+    /// Propose a rejection of the queued proposals at `nonce`. See
+    /// [`SafeTransactionData::rejection`].
+    ///
+    /// Returns the rejection alongside the unexecuted proposals it will
+    /// replace once signed to threshold and executed
+    pub async fn propose_rejection(
+        &self,
+        safe_address: Address,
+        nonce: u64,
+    ) -> SigningClientResult<RejectionProposal, S> {
+        let rejection = self
+            .propose_tx(
+                SafeTransactionData::rejection(safe_address, nonce),
+                safe_address,
+            )
+            .await?;
+
+        let replaces = self
+            .msig_history_builder()
+            .nonce(nonce)
+            .into_stream(safe_address)
+            .filter(|tx| match tx {
+                Ok(tx) => {
+                    tx.nonce == nonce
+                        && tx.safe_tx_hash != rejection.safe_tx_hash
+                        && !tx.is_executed
+                }
+                Err(_) => true,
+            })
+            .collect::<ClientResult<Vec<_>>>()
+            .await?;
+
+        Ok(RejectionProposal {
+            rejection,
+            replaces,
+        })
+    }
+
+    /// Delete a pending proposal from the service queue. Only the original
+    /// proposer may delete a proposal, and executed proposals cannot be
+    /// deleted
+    pub async fn delete_proposal(&self, safe_tx_hash: H256) -> SigningClientResult<(), S> {
+        let tx = match self.transaction_info(safe_tx_hash).await {
+            Err(ClientError::ServerErrorCode(StatusCode::NOT_FOUND)) => {
+                return Err(ClientError::ProposalNotFound(safe_tx_hash).into())
+            }
+            other => other?,
+        };
+        if tx.is_executed {
+            return Err(ClientError::ProposalAlreadyExecuted(safe_tx_hash).into());
+        }
+        let signer = self.signer.address();
+        match tx.proposer {
+            Some(proposer) if proposer != signer => {
+                return Err(ClientError::NotProposer {
+                    safe_tx_hash,
+                    proposer: Some(proposer),
+                    signer,
+                }
+                .into())
+            }
+            _ => {}
+        }
+
+        let req = DeleteProposalRequest::sign(
+            &self.signer,
+            tx.safe,
+            safe_tx_hash,
+            self.signer.chain_id(),
+        )
+        .await
+        .map_err(SigningClientError::SignerError)?;
+
+        let result: ClientResult<Option<()>> = async {
+            json_delete!(
+                self.client,
+                DeleteProposalRequest::url(self.url(), safe_tx_hash),
+                &req
+            )
+        }
+        .await;
+        // the proposal may have been executed or re-proposed since it was
+        // fetched. The service also rejects bad signatures and expired totps
+        // with a 400, so only report execution once it is confirmed
+        match result {
+            Err(ClientError::ServerErrorCode(StatusCode::NOT_FOUND)) => {
+                Err(ClientError::ProposalNotFound(safe_tx_hash).into())
+            }
+            Err(ClientError::ServerErrorCode(StatusCode::BAD_REQUEST)) => {
+                match self.transaction_info(safe_tx_hash).await {
+                    Ok(tx) if tx.is_executed => {
+                        Err(ClientError::ProposalAlreadyExecuted(safe_tx_hash).into())
+                    }
+                    _ => Err(ClientError::ServerErrorCode(StatusCode::BAD_REQUEST).into()),
+                }
+            }
+            Err(ClientError::ServerErrorCode(StatusCode::FORBIDDEN)) => {
+                Err(ClientError::NotProposer {
+                    safe_tx_hash,
+                    proposer: tx.proposer,
+                    signer,
+                }
+                .into())
+            }
+            other => other.map(|_| ()).map_err(Into::into),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ethers::signers::LocalWallet;
+
+    use super::*;
+    use crate::rpc::{
+        mock::{page, safe_info, MockService},
+        msig_history::fixtures::msig_tx_json,
+    };
+
+    const SAFE: Address = Address::repeat_byte(0x5a);
+
+    fn signer() -> LocalWallet {
+        "1c3a7cdd2270579847aaec11680312cbf4d3c36886232b413ab6529593228ec2"
+            .parse::<LocalWallet>()
+            .unwrap()
+            .with_chain_id(1u64)
+    }
+
+    /// The proposal's hash, from a `/api/v1/multisig-transactions/<hash>/`
+    /// path
+    fn tx_hash(path: &str) -> Option<H256> {
+        path.strip_prefix("/api/v1/multisig-transactions/")?
+            .trim_end_matches('/')
+            .parse()
+            .ok()
+    }
+
+    #[tokio::test]
+    async fn it_lists_only_replaced_txns_at_the_nonce() {
+        let owner = signer().address();
+        let service = MockService::start(move |path, query| {
+            if let Some(hash) = tx_hash(path) {
+                (200, msig_tx_json(SAFE, 5, hash, false, &[owner]))
+            } else if path.ends_with("/multisig-transactions/") && query.is_empty() {
+                (201, serde_json::Value::Null)
+            } else if path.ends_with("/multisig-transactions/") {
+                // as if unfiltered, with pending txns at other nonces
+                (
+                    200,
+                    page(vec![
+                        msig_tx_json(SAFE, 7, H256::repeat_byte(7), false, &[owner]),
+                        msig_tx_json(SAFE, 5, H256::repeat_byte(5), false, &[owner]),
+                        msig_tx_json(SAFE, 4, H256::repeat_byte(4), true, &[owner]),
+                    ]),
+                )
+            } else {
+                (200, safe_info(SAFE, 5, 2, &[owner]))
+            }
+        })
+        .await;
+        let client = service.client(1).with_signer(signer());
+
+        let proposal = client.propose_rejection(SAFE, 5).await.unwrap();
+        let replaced: Vec<_> = proposal.replaces.iter().map(|tx| tx.safe_tx_hash).collect();
+        assert_eq!(replaced, vec![H256::repeat_byte(5)]);
+        assert!(service
+            .requests()
+            .iter()
+            .any(|r| r.starts_with("GET ") && r.ends_with("/multisig-transactions/?nonce=5")));
+    }
+
+    #[tokio::test]
+    async fn it_queries_the_queue_from_the_safe_nonce() {
+        let owner = signer().address();
+        let service = MockService::start(move |path, _| {
+            if path.ends_with("/multisig-transactions/") {
+                (
+                    200,
+                    page(vec![
+                        msig_tx_json(SAFE, 6, H256::repeat_byte(6), false, &[owner]),
+                        msig_tx_json(SAFE, 5, H256::repeat_byte(5), false, &[]),
+                    ]),
+                )
+            } else {
+                (200, safe_info(SAFE, 5, 1, &[owner]))
+            }
+        })
+        .await;
+
+        let queue = service.client(1).queue(SAFE).await.unwrap();
+        assert!(queue.at_nonce(5).is_some() && queue.at_nonce(6).is_some());
+        assert!(service
+            .requests()
+            .iter()
+            .any(|r| r.ends_with("/multisig-transactions/?nonce__gte=5")));
+    }
+
+    /// A service holding one pending proposal, which answers its deletion
+    /// with `status`, and afterwards reports it executed or not
+    async fn deletion(status: u16, executed_after: bool) -> MockService {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let owner = signer().address();
+        let calls = AtomicUsize::new(0);
+        MockService::start(move |path, _| {
+            let hash = tx_hash(path).unwrap();
+            match calls.fetch_add(1, Ordering::SeqCst) {
+                0 => (200, msig_tx_json(SAFE, 5, hash, false, &[owner])),
+                1 => (status, serde_json::Value::Null),
+                _ => (200, msig_tx_json(SAFE, 5, hash, executed_after, &[owner])),
+            }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_maps_deletion_errors() {
+        let hash = H256::repeat_byte(1);
+
+        let service = deletion(400, false).await;
+        let client = service.client(1).with_signer(signer());
+        assert!(matches!(
+            client.delete_proposal(hash).await,
+            Err(SigningClientError::ClientError(
+                ClientError::ServerErrorCode(StatusCode::BAD_REQUEST)
+            ))
+        ));
+
+        let service = deletion(400, true).await;
+        let client = service.client(1).with_signer(signer());
+        assert!(matches!(
+            client.delete_proposal(hash).await,
+            Err(SigningClientError::ClientError(ClientError::ProposalAlreadyExecuted(h))) if h == hash
+        ));
+
+        // the fixture reports no proposer
+        let service = deletion(403, false).await;
+        let client = service.client(1).with_signer(signer());
+        assert!(matches!(
+            client.delete_proposal(hash).await,
+            Err(SigningClientError::ClientError(ClientError::NotProposer {
+                proposer: None,
+                ..
+            }))
+        ));
+    }
 }
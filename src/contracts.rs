@@ -0,0 +1,434 @@
+use ethers::{
+    abi::parse_abi,
+    contract::BaseContract,
+    types::{Address, Bytes, TransactionReceipt, H256, U256},
+    utils::keccak256,
+};
+use once_cell::sync::Lazy;
+
+use crate::rpc::{
+    common::Operations,
+    propose::{MetaTransactionData, ProposeSignature, SafeTransactionData},
+};
+
+/// Safe contract ABI, limited to execution, getters and management functions
+pub static SAFE_CONTRACT: Lazy<BaseContract> = Lazy::new(|| {
+    parse_abi(&[
+        "function execTransaction(address to, uint256 value, bytes data, uint8 operation, uint256 safeTxGas, uint256 baseGas, uint256 gasPrice, address gasToken, address refundReceiver, bytes signatures) external payable returns (bool success)",
+        "function nonce() external view returns (uint256)",
+        "function getThreshold() external view returns (uint256)",
+        "function getOwners() external view returns (address[])",
+        "function addOwnerWithThreshold(address owner, uint256 _threshold) external",
+        "function removeOwner(address prevOwner, address owner, uint256 _threshold) external",
+        "function swapOwner(address prevOwner, address oldOwner, address newOwner) external",
+        "function changeThreshold(uint256 _threshold) external",
+        "function enableModule(address module) external",
+        "function disableModule(address prevModule, address module) external",
+        "function setGuard(address guard) external",
+        "function setFallbackHandler(address handler) external",
+        "function changeMasterCopy(address _masterCopy) external",
+    ])
+    .expect("valid abi")
+    .into()
+});
+
+/// MultiSend & MultiSendCallOnly ABI
+pub static MULTISEND_CONTRACT: Lazy<BaseContract> = Lazy::new(|| {
+    parse_abi(&["function multiSend(bytes transactions) external payable"])
+        .expect("valid abi")
+        .into()
+});
+
+/// Selector of `multiSend(bytes)`
+pub const MULTISEND_SELECTOR: [u8; 4] = [0x8d, 0x80, 0xff, 0x0a];
+
+/// Canonical deployments of MultiSend & MultiSendCallOnly, v1.3.0 (incl. L2
+/// deployments) and v1.4.1
+pub static MULTISEND_ADDRESSES: Lazy<Vec<Address>> = Lazy::new(|| {
+    [
+        // v1.3.0
+        "0xA238CBeb142c10Ef7Ad8442C6D1f9E89e07e7761",
+        "0x40A2aCCbd92BCA938b02010E17A5b8929b49130D",
+        "0x998739BFdAAdde7C933B942a68053933098f9EDa",
+        "0xA1dabEF33b3B82c7814B6D82A79e50F4AC44102B",
+        // v1.4.1
+        "0x38869bf66a61cF6bDB996A6aE40D5853Fd43B526",
+        "0x9641d764fc13c8B624c04430C7356C1C7C8102e2",
+    ]
+    .iter()
+    .map(|a| a.parse().expect("valid address"))
+    .collect()
+});
+
+/// True if `address` is a canonical MultiSend deployment
+pub fn is_multisend(address: Address) -> bool {
+    MULTISEND_ADDRESSES.contains(&address)
+}
+
+/// Canonical singletons (incl. L2), each with the MultiSendCallOnly of the
+/// same deployment
+static MULTISEND_CALL_ONLY_BY_SINGLETON: Lazy<Vec<(Address, Address)>> = Lazy::new(|| {
+    [
+        // v1.3.0
+        (
+            "0xd9Db270c1B5E3Bd161E8c8503c55cEABeE709552",
+            "0x40A2aCCbd92BCA938b02010E17A5b8929b49130D",
+        ),
+        (
+            "0x3E5c63644E683549055b9Be8653de26E0B4CD36E",
+            "0x40A2aCCbd92BCA938b02010E17A5b8929b49130D",
+        ),
+        // v1.3.0, EIP-155 deployments
+        (
+            "0x69f4D1788e39c87893C980c06EdF4b7f686e2938",
+            "0xA1dabEF33b3B82c7814B6D82A79e50F4AC44102B",
+        ),
+        (
+            "0xfb1bffC9d739B8D520DaF37dF666da4C687191EA",
+            "0xA1dabEF33b3B82c7814B6D82A79e50F4AC44102B",
+        ),
+        // v1.4.1
+        (
+            "0x41675C099F32341bf84BFc5382aF534df5C7461a",
+            "0x9641d764fc13c8B624c04430C7356C1C7C8102e2",
+        ),
+        (
+            "0x29fcB43b46531BcA003ddC8FCB67FFE91900C762",
+            "0x9641d764fc13c8B624c04430C7356C1C7C8102e2",
+        ),
+    ]
+    .iter()
+    .map(|(singleton, call_only)| {
+        (
+            singleton.parse().expect("valid address"),
+            call_only.parse().expect("valid address"),
+        )
+    })
+    .collect()
+});
+
+/// The MultiSendCallOnly deployed alongside a Safe's singleton
+/// (`masterCopy`). `None` if the singleton is not a canonical deployment
+pub fn multisend_call_only(singleton: Address) -> Option<Address> {
+    MULTISEND_CALL_ONLY_BY_SINGLETON
+        .iter()
+        .find(|(s, _)| *s == singleton)
+        .map(|(_, call_only)| *call_only)
+}
+
+/// Pack transactions into the `transactions` argument of `multiSend`: each
+/// as `operation (1) || to (20) || value (32) || data length (32) || data`
+pub fn pack_multisend<'a>(txns: impl IntoIterator<Item = &'a MetaTransactionData>) -> Bytes {
+    let mut packed = vec![];
+    for tx in txns {
+        let data = tx.data.as_deref().unwrap_or_default();
+        let mut word = [0u8; 32];
+        packed.push(tx.operation.unwrap_or(Operations::Call) as u8);
+        packed.extend_from_slice(Address::from(tx.to).as_bytes());
+        tx.value.to_big_endian(&mut word);
+        packed.extend_from_slice(&word);
+        U256::from(data.len()).to_big_endian(&mut word);
+        packed.extend_from_slice(&word);
+        packed.extend_from_slice(data);
+    }
+    packed.into()
+}
+
+/// Unpack the `transactions` argument of `multiSend`. `None` if malformed
+pub fn unpack_multisend(mut packed: &[u8]) -> Option<Vec<MetaTransactionData>> {
+    const HEADER: usize = 1 + 20 + 32 + 32;
+
+    let mut txns = vec![];
+    while !packed.is_empty() {
+        if packed.len() < HEADER {
+            return None;
+        }
+        let operation = match packed[0] {
+            0 => Operations::Call,
+            1 => Operations::DelegateCall,
+            _ => return None,
+        };
+        let to = Address::from_slice(&packed[1..21]);
+        let value = U256::from_big_endian(&packed[21..53]);
+        let len = U256::from_big_endian(&packed[53..85]);
+        if len > U256::from(packed.len() - HEADER) {
+            return None;
+        }
+        let len = len.as_usize();
+        let data = &packed[HEADER..HEADER + len];
+        txns.push(MetaTransactionData {
+            to: to.into(),
+            value,
+            data: (!data.is_empty()).then(|| data.to_vec().into()),
+            operation: Some(operation),
+        });
+        packed = &packed[HEADER + len..];
+    }
+    Some(txns)
+}
+
+/// `multiSend` calldata for `txns`. Must be delegatecalled by the Safe
+pub fn multisend_calldata<'a>(txns: impl IntoIterator<Item = &'a MetaTransactionData>) -> Bytes {
+    MULTISEND_CONTRACT
+        .encode("multiSend", pack_multisend(txns))
+        .expect("valid abi encoding")
+}
+
+/// ERC20 token ABI, limited to transfers & approvals
+pub static ERC20_CONTRACT: Lazy<BaseContract> = Lazy::new(|| {
+    parse_abi(&[
+        "function transfer(address to, uint256 value) external returns (bool)",
+        "function transferFrom(address from, address to, uint256 value) external returns (bool)",
+        "function approve(address spender, uint256 value) external returns (bool)",
+        "function increaseAllowance(address spender, uint256 addedValue) external returns (bool)",
+    ])
+    .expect("valid abi")
+    .into()
+});
+
+/// ERC721 token ABI, limited to transfers
+pub static ERC721_CONTRACT: Lazy<BaseContract> = Lazy::new(|| {
+    parse_abi(&[
+        "function safeTransferFrom(address from, address to, uint256 tokenId) external",
+        "function transferFrom(address from, address to, uint256 tokenId) external",
+    ])
+    .expect("valid abi")
+    .into()
+});
+
+/// ERC1155 token ABI, limited to transfers
+pub static ERC1155_CONTRACT: Lazy<BaseContract> = Lazy::new(|| {
+    parse_abi(&[
+        "function safeTransferFrom(address from, address to, uint256 id, uint256 value, bytes data) external",
+        "function safeBatchTransferFrom(address from, address to, uint256[] ids, uint256[] values, bytes data) external",
+    ])
+    .expect("valid abi")
+    .into()
+});
+
+/// Topic of the Safe's `ExecutionSuccess(bytes32 txHash, uint256 payment)`
+/// event
+pub static EXECUTION_SUCCESS_TOPIC: Lazy<H256> =
+    Lazy::new(|| keccak256("ExecutionSuccess(bytes32,uint256)").into());
+
+/// Topic of the Safe's `ExecutionFailure(bytes32 txHash, uint256 payment)`
+/// event
+pub static EXECUTION_FAILURE_TOPIC: Lazy<H256> =
+    Lazy::new(|| keccak256("ExecutionFailure(bytes32,uint256)").into());
+
+/// Pack owner signatures in the format expected by `execTransaction`: each
+/// signature as `r || s || v`, sorted by owner address ascending
+pub fn pack_signatures<'a>(signatures: impl IntoIterator<Item = &'a ProposeSignature>) -> Bytes {
+    let mut signatures: Vec<_> = signatures.into_iter().collect();
+    signatures.sort_by_key(|s| *s.sender());
+    signatures
+        .iter()
+        .flat_map(|s| s.signature().to_vec())
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// Errors packing owner signatures for the Safe's `checkSignatures`
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SignatureError {
+    /// The signature is not a 65-byte `r || s || v` signature
+    #[error("Malformed signature by {0:?}")]
+    Malformed(Address),
+    /// Contract (EIP-1271) signatures need a dynamic part, and cannot be
+    /// packed as static signatures
+    #[error("Contract signature by {0:?} is not supported")]
+    ContractSignature(Address),
+    /// Too few signatures are by current owners
+    #[error("Only {have} of {threshold} required signatures are by current owners")]
+    BelowThreshold {
+        /// Signatures by current owners
+        have: usize,
+        /// The Safe's threshold
+        threshold: usize,
+    },
+}
+
+/// Pack `threshold` signatures by current `owners` in the format expected by
+/// the Safe's `checkSignatures`: each as `r || s || v`, sorted by owner
+/// address ascending. Signatures by other addresses, e.g. removed owners, are
+/// ignored. Errors on contract signatures, and if fewer than `threshold`
+/// owners signed
+pub fn pack_owner_signatures(
+    signatures: impl IntoIterator<Item = (Address, Bytes)>,
+    owners: &[Address],
+    threshold: u32,
+) -> Result<Bytes, SignatureError> {
+    let mut signatures: Vec<_> = signatures
+        .into_iter()
+        .filter(|(owner, _)| owners.contains(owner))
+        .collect();
+    signatures.sort_by_key(|(owner, _)| *owner);
+    signatures.dedup_by_key(|(owner, _)| *owner);
+
+    let threshold = threshold as usize;
+    if signatures.len() < threshold {
+        return Err(SignatureError::BelowThreshold {
+            have: signatures.len(),
+            threshold,
+        });
+    }
+
+    let mut packed = Vec::with_capacity(threshold * 65);
+    for (owner, signature) in signatures.into_iter().take(threshold) {
+        // v == 0 marks a contract signature, whose data follows the static
+        // part
+        if signature.len() > 65 || signature.last() == Some(&0) {
+            return Err(SignatureError::ContractSignature(owner));
+        }
+        if signature.len() != 65 {
+            return Err(SignatureError::Malformed(owner));
+        }
+        packed.extend_from_slice(&signature);
+    }
+    Ok(packed.into())
+}
+
+/// Calldata for the Safe's `execTransaction` function
+pub fn exec_transaction_calldata(tx: &SafeTransactionData, signatures: Bytes) -> Bytes {
+    SAFE_CONTRACT
+        .encode(
+            "execTransaction",
+            (
+                Address::from(tx.core.to),
+                tx.core.value,
+                tx.core.data.clone().unwrap_or_default(),
+                tx.core.operation.unwrap_or(Operations::Call) as u8,
+                tx.gas.safe_tx_gas,
+                tx.gas.base_gas,
+                tx.gas.gas_price,
+                Address::from(tx.gas.gas_token),
+                Address::from(tx.gas.refund_receiver),
+                signatures,
+            ),
+        )
+        .expect("valid abi encoding")
+}
+
+/// Determine whether the Safe transaction `safe_tx_hash` executed
+/// successfully, from the receipt of the executing transaction. `None` if the
+/// receipt contains no execution event for it
+pub fn execution_success(
+    receipt: &TransactionReceipt,
+    safe_address: Address,
+    safe_tx_hash: H256,
+) -> Option<bool> {
+    receipt
+        .logs
+        .iter()
+        .filter(|log| log.address == safe_address)
+        .filter(|log| log.data.len() >= 32 && log.data[..32] == safe_tx_hash[..])
+        .find_map(|log| match log.topics.first() {
+            Some(topic) if *topic == *EXECUTION_SUCCESS_TOPIC => Some(true),
+            Some(topic) if *topic == *EXECUTION_FAILURE_TOPIC => Some(false),
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_packs_owner_signatures() {
+        let sig = |v: u8| -> Bytes {
+            let mut sig = vec![0xaa; 64];
+            sig.push(v);
+            sig.into()
+        };
+        let (a, b, c, removed) = (
+            Address::repeat_byte(1),
+            Address::repeat_byte(2),
+            Address::repeat_byte(3),
+            Address::repeat_byte(0),
+        );
+        let owners = [c, a, b];
+
+        // removed owners are ignored, and only threshold signatures packed,
+        // sorted by owner
+        let packed = pack_owner_signatures(
+            [(c, sig(28)), (removed, sig(27)), (b, sig(27)), (a, sig(31))],
+            &owners,
+            2,
+        )
+        .unwrap();
+        assert_eq!(
+            packed.to_vec(),
+            [sig(31).to_vec(), sig(27).to_vec()].concat()
+        );
+
+        assert_eq!(
+            pack_owner_signatures([(removed, sig(27)), (a, sig(27))], &owners, 2),
+            Err(SignatureError::BelowThreshold {
+                have: 1,
+                threshold: 2
+            })
+        );
+        assert_eq!(
+            pack_owner_signatures([(a, sig(0))], &owners, 1),
+            Err(SignatureError::ContractSignature(a))
+        );
+        assert_eq!(
+            pack_owner_signatures([(a, Bytes::from(vec![1, 2, 3]))], &owners, 1),
+            Err(SignatureError::Malformed(a))
+        );
+    }
+
+    #[test]
+    fn it_resolves_multisend_call_only() {
+        for (_, call_only) in MULTISEND_CALL_ONLY_BY_SINGLETON.iter() {
+            assert!(is_multisend(*call_only));
+        }
+        assert_eq!(
+            multisend_call_only(
+                "0x3E5c63644E683549055b9Be8653de26E0B4CD36E"
+                    .parse()
+                    .unwrap()
+            ),
+            Some(MULTISEND_ADDRESSES[1])
+        );
+        assert_eq!(multisend_call_only(Address::zero()), None);
+    }
+
+    #[test]
+    fn it_encodes_exec_transaction() {
+        let tx = SafeTransactionData::rejection(Address::repeat_byte(0x11), 7);
+        let calldata = exec_transaction_calldata(&tx, Bytes::default());
+        // execTransaction selector
+        assert_eq!(calldata[..4], [0x6a, 0x76, 0x12, 0x02]);
+    }
+
+    #[test]
+    fn it_packs_multisend() {
+        let txns = vec![
+            MetaTransactionData {
+                to: Address::repeat_byte(0x11).into(),
+                value: U256::exp10(20),
+                data: Some(vec![0xde, 0xad, 0xbe, 0xef].into()),
+                operation: Some(Operations::Call),
+            },
+            MetaTransactionData {
+                to: Address::repeat_byte(0x22).into(),
+                value: U256::zero(),
+                data: None,
+                operation: Some(Operations::DelegateCall),
+            },
+        ];
+        let packed = pack_multisend(&txns);
+        assert_eq!(packed.len(), 85 + 4 + 85);
+
+        let unpacked = unpack_multisend(&packed).unwrap();
+        assert_eq!(unpacked.len(), 2);
+        assert_eq!(unpacked[0].value, U256::exp10(20));
+        assert_eq!(unpacked[0].data, txns[0].data);
+        assert_eq!(unpacked[1].operation, Some(Operations::DelegateCall));
+        assert!(unpacked[1].data.is_none());
+
+        assert!(unpack_multisend(&packed[..packed.len() - 1]).is_none());
+        assert_eq!(multisend_calldata(&txns)[..4], MULTISEND_SELECTOR);
+    }
+}
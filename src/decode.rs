@@ -0,0 +1,487 @@
+use std::{collections::HashMap, fmt::Write, path::Path};
+
+use ethers::{
+    abi::{Abi, Function, Token},
+    types::{Address, Bytes, I256, U256},
+};
+
+use crate::{
+    contracts::{
+        unpack_multisend, ERC1155_CONTRACT, ERC20_CONTRACT, ERC721_CONTRACT, MULTISEND_CONTRACT,
+        MULTISEND_SELECTOR, SAFE_CONTRACT,
+    },
+    rpc::{
+        common::Operations,
+        msig_history::{
+            DecodedData, DecodedTransaction, MsigTxResponse, Parameter, ParameterValue,
+        },
+    },
+};
+
+/// Decoder errors
+#[derive(Debug, thiserror::Error)]
+pub enum DecodeError {
+    /// Filesystem error
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    /// Invalid ABI JSON
+    #[error("{0}")]
+    SerdeError(#[from] serde_json::Error),
+}
+
+impl From<Token> for ParameterValue {
+    fn from(token: Token) -> Self {
+        match token {
+            Token::Address(a) => ParameterValue::Address(a),
+            Token::Uint(u) => ParameterValue::Uint(u),
+            Token::Int(i) => ParameterValue::Int(I256::from_raw(i)),
+            Token::Bool(b) => ParameterValue::Bool(b),
+            Token::Bytes(b) | Token::FixedBytes(b) => ParameterValue::Bytes(b.into()),
+            Token::String(s) => ParameterValue::String(s),
+            Token::Array(items) | Token::FixedArray(items) => {
+                ParameterValue::Array(items.into_iter().map(Into::into).collect())
+            }
+            Token::Tuple(items) => {
+                ParameterValue::Tuple(items.into_iter().map(Into::into).collect())
+            }
+        }
+    }
+}
+
+/// Decodes calldata locally, from known ABIs. Includes ERC20, ERC721,
+/// ERC1155, Safe management and MultiSend functions by default. MultiSend
+/// calls are unpacked and their transactions decoded recursively
+#[derive(Debug, Clone)]
+pub struct Decoder {
+    functions: HashMap<[u8; 4], Function>,
+}
+
+/// Selector of `transferFrom(address,address,uint256)`, shared by ERC20 and
+/// ERC721
+const TRANSFER_FROM_SELECTOR: [u8; 4] = [0x23, 0xb8, 0x72, 0xdd];
+
+impl Default for Decoder {
+    fn default() -> Self {
+        // later ABIs replace earlier ones, so ERC20 decodes the shared
+        // `transferFrom`
+        let mut decoder = [
+            &*ERC721_CONTRACT,
+            &*ERC20_CONTRACT,
+            &*ERC1155_CONTRACT,
+            &*SAFE_CONTRACT,
+            &*MULTISEND_CONTRACT,
+        ]
+        .into_iter()
+        .fold(Self::empty(), |decoder, contract| {
+            decoder.with_abi(contract.abi())
+        });
+        // the token standard is unknown from calldata alone
+        if let Some(function) = decoder.functions.get_mut(&TRANSFER_FROM_SELECTOR) {
+            function.inputs[2].name = "valueOrTokenId".to_owned();
+        }
+        decoder
+    }
+}
+
+impl Decoder {
+    /// Instantiate a decoder with no known ABIs
+    pub fn empty() -> Self {
+        Self {
+            functions: Default::default(),
+        }
+    }
+
+    /// Add the functions of an ABI, e.g. `abigen` bindings' `*_ABI`. Replaces
+    /// known functions with the same selector
+    pub fn with_abi(mut self, abi: &Abi) -> Self {
+        for function in abi.functions() {
+            self.functions
+                .insert(function.short_signature(), function.clone());
+        }
+        self
+    }
+
+    /// Add the functions of a JSON ABI. Accepts both a bare ABI and a
+    /// compiler artifact with an `abi` key
+    pub fn with_abi_json(self, json: &str) -> Result<Self, DecodeError> {
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum AbiJson {
+            Bare(Abi),
+            Artifact { abi: Abi },
+        }
+
+        let abi = match serde_json::from_str(json)? {
+            AbiJson::Bare(abi) | AbiJson::Artifact { abi } => abi,
+        };
+        Ok(self.with_abi(&abi))
+    }
+
+    /// Add the functions of a JSON ABI file. See [`Decoder::with_abi_json`]
+    pub fn with_abi_file(self, path: impl AsRef<Path>) -> Result<Self, DecodeError> {
+        self.with_abi_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// Decode calldata. `None` if the selector is unknown or the arguments do
+    /// not match the function's ABI
+    pub fn decode_data(&self, data: &[u8]) -> Option<DecodedData> {
+        if data.len() < 4 {
+            return None;
+        }
+        let selector: [u8; 4] = data[..4].try_into().expect("checked length");
+        let function = self.functions.get(&selector)?;
+        let tokens = function.decode_input(&data[4..]).ok()?;
+
+        let nested = match tokens.first() {
+            Some(Token::Bytes(packed)) if selector == MULTISEND_SELECTOR => {
+                self.decode_multisend(packed)
+            }
+            _ => None,
+        };
+
+        let parameters = function
+            .inputs
+            .iter()
+            .zip(tokens)
+            .map(|(input, token)| Parameter {
+                name: input.name.clone(),
+                param_type: input.kind.to_string(),
+                value: token.into(),
+                value_decoded: nested.clone(),
+                value_decoded_raw: None,
+            })
+            .collect();
+
+        Some(DecodedData {
+            method: function.name.clone(),
+            parameters,
+        })
+    }
+
+    /// Unpack and decode the `transactions` argument of `multiSend`
+    pub fn decode_multisend(&self, packed: &[u8]) -> Option<Vec<DecodedTransaction>> {
+        Some(
+            unpack_multisend(packed)?
+                .into_iter()
+                .map(|tx| DecodedTransaction {
+                    operation: tx.operation.unwrap_or(Operations::Call),
+                    to: tx.to.into(),
+                    value: tx.value,
+                    data_decoded: tx.data.as_deref().and_then(|d| self.decode_data(d)),
+                    data: tx.data,
+                })
+                .collect(),
+        )
+    }
+
+    /// Decode the data of a multisig transaction
+    pub fn decode_tx(&self, tx: &MsigTxResponse) -> Option<DecodedData> {
+        self.decode_data(tx.data.as_deref()?)
+    }
+
+    /// Describe a multisig transaction, decoding its data locally
+    pub fn describe_tx(&self, tx: &MsigTxResponse) -> String {
+        describe_call(
+            tx.operation,
+            tx.to,
+            tx.value,
+            tx.data.as_ref(),
+            self.decode_tx(tx).as_ref(),
+        )
+    }
+
+    /// Compare the local decoding of a multisig transaction with the service's
+    /// `data_decoded`. Empty if they agree
+    pub fn compare_with_service(&self, tx: &MsigTxResponse) -> Vec<DecodeMismatch> {
+        let mut mismatches = vec![];
+        compare_decoded(
+            "",
+            self.decode_tx(tx).as_ref(),
+            tx.data_decoded.as_ref(),
+            &mut mismatches,
+        );
+        mismatches
+    }
+}
+
+/// A difference between the local and service decodings of a transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeMismatch {
+    /// Location of the difference, e.g. `transactions[1].to`
+    pub path: String,
+    /// Local decoding, `None` if not decoded locally
+    pub local: Option<String>,
+    /// Service decoding, `None` if not decoded by the service
+    pub service: Option<String>,
+}
+
+fn mismatch(
+    path: &str,
+    field: &str,
+    local: Option<String>,
+    service: Option<String>,
+) -> DecodeMismatch {
+    let path = if path.is_empty() {
+        field.to_owned()
+    } else {
+        format!("{path}.{field}")
+    };
+    DecodeMismatch {
+        path,
+        local,
+        service,
+    }
+}
+
+fn compare_decoded(
+    path: &str,
+    local: Option<&DecodedData>,
+    service: Option<&DecodedData>,
+    out: &mut Vec<DecodeMismatch>,
+) {
+    let (local, service) = match (local, service) {
+        (None, None) => return,
+        (Some(local), Some(service)) => (local, service),
+        (local, service) => {
+            out.push(mismatch(
+                path,
+                "method",
+                local.map(|d| d.method.clone()),
+                service.map(|d| d.method.clone()),
+            ));
+            return;
+        }
+    };
+
+    if local.method != service.method || local.parameters.len() != service.parameters.len() {
+        out.push(mismatch(
+            path,
+            "method",
+            Some(describe(local)),
+            Some(describe(service)),
+        ));
+        return;
+    }
+
+    for (local, service) in local.parameters.iter().zip(service.parameters.iter()) {
+        // the service returns some types undecoded. Only typed values are
+        // comparable
+        let comparable = !matches!(local.value, ParameterValue::Raw(_))
+            && !matches!(service.value, ParameterValue::Raw(_));
+        if comparable && local.value != service.value {
+            out.push(mismatch(
+                path,
+                &local.name,
+                Some(local.value.to_string()),
+                Some(service.value.to_string()),
+            ));
+        }
+
+        let local_txns = local.value_decoded.as_deref().unwrap_or_default();
+        let service_txns = service.value_decoded.as_deref().unwrap_or_default();
+        if local_txns.len() != service_txns.len() {
+            out.push(mismatch(
+                path,
+                &format!("{}.length", local.name),
+                Some(local_txns.len().to_string()),
+                Some(service_txns.len().to_string()),
+            ));
+            continue;
+        }
+        for (i, (l, s)) in local_txns.iter().zip(service_txns.iter()).enumerate() {
+            let tx_path = mismatch(path, &format!("{}[{i}]", local.name), None, None).path;
+            if l.operation != s.operation {
+                out.push(mismatch(
+                    &tx_path,
+                    "operation",
+                    Some(format!("{:?}", l.operation)),
+                    Some(format!("{:?}", s.operation)),
+                ));
+            }
+            if l.to != s.to {
+                out.push(mismatch(
+                    &tx_path,
+                    "to",
+                    Some(format!("{:?}", l.to)),
+                    Some(format!("{:?}", s.to)),
+                ));
+            }
+            if l.value != s.value {
+                out.push(mismatch(
+                    &tx_path,
+                    "value",
+                    Some(l.value.to_string()),
+                    Some(s.value.to_string()),
+                ));
+            }
+            if l.data.as_deref().unwrap_or_default() != s.data.as_deref().unwrap_or_default() {
+                out.push(mismatch(
+                    &tx_path,
+                    "data",
+                    l.data.as_ref().map(ToString::to_string),
+                    s.data.as_ref().map(ToString::to_string),
+                ));
+                continue;
+            }
+            compare_decoded(
+                &tx_path,
+                l.data_decoded.as_ref(),
+                s.data_decoded.as_ref(),
+                out,
+            );
+        }
+    }
+}
+
+/// Describe a decoded call as `method(name: value, ...)`. Nested multiSend
+/// transactions are listed on subsequent indented lines
+pub fn describe(decoded: &DecodedData) -> String {
+    let mut out = String::new();
+    write_decoded(&mut out, decoded, 0);
+    out
+}
+
+/// Describe a call: its operation, target, value, and decoded data if
+/// available, otherwise its raw data
+pub fn describe_call(
+    operation: Operations,
+    to: Address,
+    value: U256,
+    data: Option<&Bytes>,
+    decoded: Option<&DecodedData>,
+) -> String {
+    let mut out = String::new();
+    write_call(&mut out, operation, to, value, data, decoded, 0);
+    out
+}
+
+fn write_call(
+    out: &mut String,
+    operation: Operations,
+    to: Address,
+    value: U256,
+    data: Option<&Bytes>,
+    decoded: Option<&DecodedData>,
+    depth: usize,
+) {
+    let op = match operation {
+        Operations::Call => "CALL",
+        Operations::DelegateCall => "DELEGATECALL",
+    };
+    let _ = write!(out, "{op} {}", ethers::utils::to_checksum(&to, None));
+    if !value.is_zero() {
+        let _ = write!(out, " value={value}");
+    }
+    match (decoded, data) {
+        (Some(decoded), _) => {
+            out.push_str(": ");
+            write_decoded(out, decoded, depth);
+        }
+        (None, Some(data)) if !data.is_empty() => {
+            let _ = write!(out, " data={data}");
+        }
+        _ => {}
+    }
+}
+
+fn write_decoded(out: &mut String, decoded: &DecodedData, depth: usize) {
+    let _ = write!(out, "{}(", decoded.method);
+    for (i, param) in decoded.parameters.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        match &param.value_decoded {
+            Some(txns) => {
+                let _ = write!(out, "{}: <{} transactions>", param.name, txns.len());
+            }
+            None => {
+                let _ = write!(out, "{}: {}", param.name, param.value);
+            }
+        }
+    }
+    out.push(')');
+
+    for (i, tx) in decoded.nested_transactions().enumerate() {
+        let _ = write!(out, "\n{}[{i}] ", "  ".repeat(depth + 1));
+        write_call(
+            out,
+            tx.operation,
+            tx.to,
+            tx.value,
+            tx.data.as_ref(),
+            tx.data_decoded.as_ref(),
+            depth + 1,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{contracts::multisend_calldata, tx::erc20_transfer, tx::native_transfer};
+
+    #[test]
+    fn it_decodes_multisend_locally() {
+        let usdc: Address = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"
+            .parse()
+            .unwrap();
+        let recipient = Address::repeat_byte(0x22);
+        let txns = vec![
+            erc20_transfer(usdc, recipient, 5_000_000.into()),
+            native_transfer(recipient, U256::exp10(18)),
+        ];
+        let data = multisend_calldata(&txns);
+
+        let decoder = Decoder::default();
+        let decoded = decoder.decode_data(&data).unwrap();
+        assert_eq!(decoded.method, "multiSend");
+
+        let nested: Vec<_> = decoded.nested_transactions().collect();
+        assert_eq!(nested.len(), 2);
+        let transfer = nested[0].data_decoded.as_ref().unwrap();
+        assert_eq!(transfer.method, "transfer");
+        assert_eq!(
+            transfer.parameters[1].value,
+            ParameterValue::Uint(5_000_000.into())
+        );
+        assert!(nested[1].data_decoded.is_none());
+
+        let description = describe(&decoded);
+        assert_eq!(description.lines().count(), 3);
+        assert!(description.starts_with("multiSend(transactions: <2 transactions>)"));
+
+        let mut mismatches = vec![];
+        compare_decoded("", Some(&decoded), Some(&decoded), &mut mismatches);
+        assert!(mismatches.is_empty());
+
+        let mut tampered = decoded.clone();
+        tampered.parameters[0].value_decoded.as_mut().unwrap()[1].value = U256::one();
+        compare_decoded("", Some(&decoded), Some(&tampered), &mut mismatches);
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].path, "transactions[1].value");
+    }
+
+    #[test]
+    fn it_decodes_transfer_from_as_erc20() {
+        let (from, to) = (Address::repeat_byte(0x11), Address::repeat_byte(0x22));
+        let data = ERC20_CONTRACT
+            .encode("transferFrom", (from, to, U256::from(5_000_000)))
+            .unwrap();
+        assert_eq!(data[..4], TRANSFER_FROM_SELECTOR);
+        // identical calldata for ERC721
+        assert_eq!(
+            data,
+            ERC721_CONTRACT
+                .encode("transferFrom", (from, to, U256::from(5_000_000)))
+                .unwrap()
+        );
+
+        let decoded = Decoder::default().decode_data(&data).unwrap();
+        assert_eq!(decoded.method, "transferFrom");
+        assert_eq!(decoded.parameters[2].name, "valueOrTokenId");
+        assert_eq!(
+            decoded.parameters[2].value,
+            ParameterValue::Uint(5_000_000.into())
+        );
+    }
+}
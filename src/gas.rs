@@ -0,0 +1,383 @@
+use ethers::{
+    providers::{call_raw::spoof, Middleware, ProviderError},
+    types::{
+        transaction::eip2718::TypedTransaction, Address, BlockNumber, Bytes, TransactionRequest,
+        U256,
+    },
+};
+
+use crate::{
+    contracts::{exec_transaction_calldata, SAFE_CONTRACT},
+    rpc::{
+        common::Operations,
+        propose::{MetaTransactionData, SafeTransactionData},
+    },
+};
+
+/// Local gas estimation errors
+#[derive(Debug, thiserror::Error)]
+pub enum EstimationError<M: Middleware> {
+    /// Thrown when the middleware errors
+    #[error("{0}")]
+    MiddlewareError(M::Error),
+    /// Thrown when a raw RPC request errors, e.g. if the node does not
+    /// support state overrides in `eth_estimateGas`
+    #[error("{0}")]
+    ProviderError(#[from] ProviderError),
+    /// Delegatecall target has no code, so the call cannot be simulated
+    #[error("Delegatecall target {0:?} has no code")]
+    NoCode(Address),
+    /// The Safe returned an unexpected value
+    #[error("Could not decode Safe {0} response")]
+    Decode(&'static str),
+    /// A gas amount does not fit in 64 bits
+    #[error("Gas amount {0} exceeds u64")]
+    GasOverflow(U256),
+}
+
+/// Convert a gas amount to `u64`, erroring rather than truncating
+pub fn gas_to_u64<M: Middleware>(gas: U256) -> Result<u64, EstimationError<M>> {
+    u64::try_from(gas).map_err(|_| EstimationError::GasOverflow(gas))
+}
+
+// Base gas accounting follows the Safe's `estimateTxBaseGas` in
+// safe-core-sdk (protocol-kit `utils/transactions/gas`), itself ported from
+// the Safe relay service. The Safe contracts charge `baseGas` as a flat
+// amount, so these are estimates rather than exact costs
+
+/// Intrinsic gas of an ethereum transaction
+pub const TX_BASE_GAS: u64 = 21_000;
+
+/// Gas to check a single owner signature: calldata, memory expansion for
+/// the signature, and `ecrecover`
+pub const SIGNATURE_CHECK_GAS: u64 = 68 + 2176 + 2176 + 6000;
+
+/// Gas to update the Safe's nonce. Initializing a zero storage slot costs
+/// more than updating it
+pub const NONCE_UPDATE_GAS: u64 = 5_000;
+
+/// Gas to initialize the Safe's nonce, for its first transaction
+pub const NONCE_INIT_GAS: u64 = 20_000;
+
+/// Gas to hash the Safe transaction, emit the execution event, and pay the
+/// refund
+pub const EXEC_OVERHEAD_GAS: u64 = 2_500 + 500;
+
+/// Calldata gas cost: 4 per zero byte, 16 per nonzero byte, per EIP-2028
+pub fn calldata_cost(data: &[u8]) -> u64 {
+    data.iter().map(|b| if *b == 0 { 4 } else { 16 }).sum()
+}
+
+/// Estimate `baseGas` for a Safe transaction signed by `threshold` owners.
+///
+/// This is the gas used by `execTransaction` outside the inner call, and
+/// refunded to the executor alongside `safeTxGas` when `gasPrice` is
+/// nonzero: calldata of the `execTransaction` call, signature checks, nonce
+/// update, and hashing & refund overhead. The intrinsic gas of the executing
+/// transaction is not included
+pub fn estimate_base_gas(tx: &SafeTransactionData, threshold: u32) -> u64 {
+    // signatures are accounted for separately
+    let calldata = exec_transaction_calldata(tx, Bytes::default());
+    let nonce_gas = if tx.nonce == 0 {
+        NONCE_INIT_GAS
+    } else {
+        NONCE_UPDATE_GAS
+    };
+
+    calldata_cost(&calldata)
+        + threshold as u64 * SIGNATURE_CHECK_GAS
+        + nonce_gas
+        + EXEC_OVERHEAD_GAS
+}
+
+/// Convert the gas estimate of the inner call made directly from the Safe
+/// into the gas used by the Safe to make the same call. This removes the
+/// intrinsic and calldata gas, which the Safe does not pay for internal calls
+pub fn inner_call_gas(estimate: u64, data: &[u8]) -> u64 {
+    estimate
+        .saturating_sub(TX_BASE_GAS)
+        .saturating_sub(calldata_cost(data))
+}
+
+/// Estimate the total gas of the `execTransaction` call executing `tx`,
+/// given the gas used by its inner call: the intrinsic gas, `baseGas`
+/// (which covers signatures and overhead), and the inner call.
+///
+/// Adds a 1/63 margin on the inner call, as the Safe checks that enough gas
+/// remains to forward `safeTxGas` after the EIP-150 deduction
+pub fn estimate_exec_gas(tx: &SafeTransactionData, threshold: u32, inner_gas: u64) -> u64 {
+    TX_BASE_GAS + estimate_base_gas(tx, threshold) + inner_gas + inner_gas / 63
+}
+
+/// Estimate `safeTxGas` for a transaction of the Safe at `safe_address`:
+/// the gas used by the inner call.
+///
+/// Calls are estimated from the Safe via `eth_estimateGas`. Delegatecalls are
+/// estimated by overriding the Safe's code with the target's code, so that the
+/// target runs against the Safe's storage and balance. This requires the node
+/// to support state overrides in `eth_estimateGas`
+pub async fn estimate_safe_tx_gas<M: Middleware>(
+    provider: &M,
+    safe_address: Address,
+    tx: &MetaTransactionData,
+) -> Result<u64, EstimationError<M>> {
+    let data = tx.data.clone().unwrap_or_default();
+    let to = Address::from(tx.to);
+
+    let estimate = match tx.operation.unwrap_or(Operations::Call) {
+        Operations::Call => {
+            let call: TypedTransaction = TransactionRequest::new()
+                .from(safe_address)
+                .to(to)
+                .value(tx.value)
+                .data(data.clone())
+                .into();
+            provider
+                .estimate_gas(&call, None)
+                .await
+                .map_err(EstimationError::MiddlewareError)?
+        }
+        Operations::DelegateCall => {
+            let code = provider
+                .get_code(to, None)
+                .await
+                .map_err(EstimationError::MiddlewareError)?;
+            if code.is_empty() {
+                return Err(EstimationError::NoCode(to));
+            }
+            let state = spoof::code(safe_address, code);
+            // value is not transferred by delegatecall
+            let call: TypedTransaction = TransactionRequest::new()
+                .to(safe_address)
+                .data(data.clone())
+                .into();
+            provider
+                .provider()
+                .request::<_, U256>("eth_estimateGas", (call, BlockNumber::Latest, state))
+                .await?
+        }
+    };
+
+    Ok(inner_call_gas(gas_to_u64(estimate)?, &data))
+}
+
+/// Read the current threshold of the Safe at `safe_address`
+pub async fn safe_threshold<M: Middleware>(
+    provider: &M,
+    safe_address: Address,
+) -> Result<u32, EstimationError<M>> {
+    let call: TypedTransaction = TransactionRequest::new()
+        .to(safe_address)
+        .data(
+            SAFE_CONTRACT
+                .encode("getThreshold", ())
+                .expect("valid abi encoding"),
+        )
+        .into();
+    let output = provider
+        .call(&call, None)
+        .await
+        .map_err(EstimationError::MiddlewareError)?;
+    let threshold: U256 = SAFE_CONTRACT
+        .decode_output("getThreshold", output)
+        .map_err(|_| EstimationError::Decode("getThreshold"))?;
+    Ok(threshold.low_u32())
+}
+
+/// Estimates `safeTxGas` and `baseGas` locally, by simulating the inner call
+/// of a Safe transaction via an ethers provider. Does not depend on the
+/// Safe Transaction Service
+#[derive(Debug, Clone)]
+pub struct GasEstimator<M> {
+    provider: M,
+    safe_address: Address,
+}
+
+impl<M> GasEstimator<M>
+where
+    M: Middleware,
+{
+    /// Instantiate an estimator for txns of the Safe at `safe_address`
+    pub fn new(provider: M, safe_address: Address) -> Self {
+        Self {
+            provider,
+            safe_address,
+        }
+    }
+
+    /// Estimate `safeTxGas`. See [`estimate_safe_tx_gas`]
+    pub async fn safe_tx_gas(&self, tx: &MetaTransactionData) -> Result<u64, EstimationError<M>> {
+        estimate_safe_tx_gas(&self.provider, self.safe_address, tx).await
+    }
+
+    /// Read the Safe's current threshold
+    pub async fn threshold(&self) -> Result<u32, EstimationError<M>> {
+        safe_threshold(&self.provider, self.safe_address).await
+    }
+
+    /// Set the gas fields of `tx`: `safeTxGas` from simulation, and
+    /// `baseGas` for the Safe's current threshold if `gasPrice` is nonzero.
+    /// `baseGas` is only used to compute the executor's refund
+    pub async fn fill(&self, tx: &mut SafeTransactionData) -> Result<(), EstimationError<M>> {
+        tx.gas.safe_tx_gas = self.safe_tx_gas(&tx.core).await?.into();
+        if !tx.gas.gas_price.is_zero() {
+            tx.gas.base_gas = estimate_base_gas(tx, self.threshold().await?).into();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ethers::{
+        abi::Token,
+        providers::{MockProvider, Provider},
+    };
+
+    use super::*;
+    use crate::rpc::propose::SafeGasConfig;
+
+    const SAFE: Address = Address::repeat_byte(0x5a);
+    const TARGET: Address = Address::repeat_byte(0x11);
+
+    fn meta_tx(operation: Operations, data: &[u8]) -> MetaTransactionData {
+        MetaTransactionData {
+            to: TARGET.into(),
+            value: U256::zero(),
+            data: (!data.is_empty()).then(|| data.to_vec().into()),
+            operation: Some(operation),
+        }
+    }
+
+    fn safe_tx(nonce: u64, gas_price: u64) -> SafeTransactionData {
+        SafeTransactionData {
+            core: meta_tx(Operations::Call, &[]),
+            gas: SafeGasConfig {
+                gas_price: gas_price.into(),
+                ..Default::default()
+            },
+            nonce,
+        }
+    }
+
+    fn mocked() -> (GasEstimator<Provider<MockProvider>>, MockProvider) {
+        let (provider, mock) = Provider::mocked();
+        (GasEstimator::new(provider, SAFE), mock)
+    }
+
+    #[test]
+    fn it_prices_calldata() {
+        assert_eq!(calldata_cost(&[0, 0, 1, 0xff]), 4 + 4 + 16 + 16);
+        assert_eq!(inner_call_gas(21_000 + 32 + 100, &[1, 1]), 100);
+        assert_eq!(inner_call_gas(100, &[1]), 0);
+    }
+
+    #[test]
+    fn it_errors_on_gas_overflow() {
+        type M = ethers::providers::Provider<ethers::providers::MockProvider>;
+        assert_eq!(gas_to_u64::<M>(U256::from(u64::MAX)).unwrap(), u64::MAX);
+        assert!(matches!(
+            gas_to_u64::<M>(U256::from(u64::MAX) + 1),
+            Err(EstimationError::GasOverflow(_))
+        ));
+    }
+
+    #[test]
+    fn it_estimates_base_gas() {
+        // execTransaction with empty data and signatures: 388 bytes, of
+        // which the selector, target and two offsets make 28 nonzero
+        let calldata = 28 * 16 + 360 * 4;
+        assert_eq!(
+            calldata_cost(&exec_transaction_calldata(&safe_tx(1, 0), Bytes::default())),
+            calldata
+        );
+
+        let base = calldata + 2 * SIGNATURE_CHECK_GAS + NONCE_UPDATE_GAS + EXEC_OVERHEAD_GAS;
+        assert_eq!(estimate_base_gas(&safe_tx(1, 0), 2), base);
+        assert_eq!(
+            estimate_base_gas(&safe_tx(1, 0), 3),
+            base + SIGNATURE_CHECK_GAS
+        );
+        // the first tx initializes the nonce
+        assert_eq!(
+            estimate_base_gas(&safe_tx(0, 0), 2),
+            base - NONCE_UPDATE_GAS + NONCE_INIT_GAS
+        );
+    }
+
+    #[test]
+    fn it_estimates_exec_gas() {
+        let tx = safe_tx(1, 0);
+        assert_eq!(
+            estimate_exec_gas(&tx, 2, 63_000),
+            TX_BASE_GAS + estimate_base_gas(&tx, 2) + 63_000 + 1_000
+        );
+    }
+
+    #[tokio::test]
+    async fn it_fills_base_gas_only_with_a_gas_price() {
+        let threshold = Bytes::from(ethers::abi::encode(&[Token::Uint(2.into())]));
+
+        let (estimator, mock) = mocked();
+        mock.push::<Bytes, _>(threshold).unwrap();
+        mock.push(U256::from(TX_BASE_GAS + 5_000)).unwrap();
+        let mut tx = safe_tx(1, 1);
+        estimator.fill(&mut tx).await.unwrap();
+        assert_eq!(tx.gas.safe_tx_gas, 5_000.into());
+        // priced before baseGas itself is set
+        let mut unfilled = tx.clone();
+        unfilled.gas.base_gas = U256::zero();
+        assert_eq!(tx.gas.base_gas, estimate_base_gas(&unfilled, 2).into());
+
+        let (estimator, mock) = mocked();
+        mock.push(U256::from(TX_BASE_GAS + 5_000)).unwrap();
+        let mut tx = safe_tx(1, 0);
+        estimator.fill(&mut tx).await.unwrap();
+        assert_eq!(tx.gas.safe_tx_gas, 5_000.into());
+        assert!(tx.gas.base_gas.is_zero());
+        // the threshold is not read
+        let call: TypedTransaction = TransactionRequest::new()
+            .from(SAFE)
+            .to(TARGET)
+            .value(U256::zero())
+            .data(Bytes::default())
+            .into();
+        mock.assert_request("eth_estimateGas", [call]).unwrap();
+        assert!(mock.assert_request("eth_call", ()).is_err());
+    }
+
+    #[tokio::test]
+    async fn it_estimates_delegatecalls_with_the_target_code() {
+        let data = [0xde, 0xad, 0, 0];
+        let code = Bytes::from(vec![0x60, 0x00]);
+
+        let (estimator, mock) = mocked();
+        mock.push(U256::from(TX_BASE_GAS + 16 + 16 + 4 + 4 + 7_000))
+            .unwrap();
+        mock.push::<Bytes, _>(code.clone()).unwrap();
+        let gas = estimator
+            .safe_tx_gas(&meta_tx(Operations::DelegateCall, &data))
+            .await
+            .unwrap();
+        assert_eq!(gas, 7_000);
+
+        mock.assert_request("eth_getCode", (TARGET, "latest"))
+            .unwrap();
+        // run against the Safe, with the Safe's code replaced by the target's
+        let call: TypedTransaction = TransactionRequest::new()
+            .to(SAFE)
+            .data(data.to_vec())
+            .into();
+        mock.assert_request("eth_estimateGas", (call, "latest", spoof::code(SAFE, code)))
+            .unwrap();
+
+        let (estimator, mock) = mocked();
+        mock.push::<Bytes, _>(Bytes::default()).unwrap();
+        assert!(matches!(
+            estimator
+                .safe_tx_gas(&meta_tx(Operations::DelegateCall, &data))
+                .await,
+            Err(EstimationError::NoCode(to)) if to == TARGET
+        ));
+    }
+}
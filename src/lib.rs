@@ -14,9 +14,39 @@ pub mod rpc;
 /// ethers middleware
 pub mod middleware;
 
+/// Safe contract ABIs & calldata encoding
+pub mod contracts;
+
+/// Local calldata decoding
+pub mod decode;
+
+/// Transaction policies enforced before signing
+pub mod policy;
+
+/// Detection of dangerous transactions before signing
+pub mod safety;
+
+/// Human-readable transaction previews
+pub mod preview;
+
 /// Network configuration
 pub mod networks;
 
+/// Offline proposal storage & portable signed bundles
+pub mod offline;
+
+/// Safe transaction gas estimation
+pub mod gas;
+
+/// Token & native transfer builders
+pub mod tx;
+
+/// Transaction Builder JSON batch import & export
+pub mod batch;
+
+/// Watch transactions for confirmations & execution
+pub mod watch;
+
 pub use client::{ClientError, SafeClient, SigningClient, SigningClientError};
 
 // currently supported:
@@ -24,3 +54,13 @@ pub use client::{ClientError, SafeClient, SigningClient, SigningClientError};
 // GET `/v1/safes/{address}/multisig-transactions`
 // POST `/v1/safes/{address}/multisig-transactions`
 // POST `/v1/safes/{:?}/multisig-transactions/estimations/`
+// GET `/v1/delegates/`
+// POST `/v1/delegates/`
+// DELETE `/v1/delegates/{delegate}/`
+// DELETE `/v1/multisig-transactions/{safe_tx_hash}/`
+// POST `/v1/multisig-transactions/{safe_tx_hash}/confirmations/`
+// POST `/v1/data-decoder/`
+// GET `/v1/safes/{address}/messages/`
+// POST `/v1/safes/{address}/messages/`
+// GET `/v1/messages/{message_hash}/`
+// POST `/v1/messages/{message_hash}/signatures/`
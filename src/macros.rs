@@ -49,7 +49,7 @@ macro_rules! json_get {
         let mut url = $url.clone();
         url.query_pairs_mut().extend_pairs($query);
         tracing::debug!(url = url.as_str(), "Dispatching api request");
-        let resp = $client.get($url).send().await?;
+        let resp = $client.get(url.clone()).send().await?;
         let status = resp.status();
         match status.as_u16() {
             0..=399 => {}, // non-error codes
@@ -76,6 +76,42 @@ macro_rules! json_get {
     }};
 }
 
+#[macro_export]
+/// Make a DELETE request sending and expecting JSON.
+/// if JSON deser fails, emit a `WARN` level tracing event
+macro_rules! json_delete {
+    ($client:expr, $url:expr, $params:expr,) => {
+        json_delete!($client, $url, $params)
+    };
+    ($client:expr, $url:expr, $params:expr) => {{
+        let url = $url;
+        tracing::debug!(body = serde_json::to_string(&$params).unwrap().as_str());
+
+        let resp = $client.delete(url.clone()).json($params).send().await?;
+        let status = resp.status();
+        match status.as_u16() {
+            0..=399 => {}, // non-error codes
+            422 => {}, // do nothing, these are handled later
+            400.. => return Err($crate::ClientError::ServerErrorCode(status).into())
+        };
+        let text = resp.text().await?;
+
+        let result: $crate::rpc::common::ApiResponse<_> = text.parse()?;
+
+        // json deser fails
+        if result.is_err() {
+            tracing::warn!(
+                method = "DELETE",
+                url = %url,
+                params = serde_json::to_string(&$params).unwrap().as_str(),
+                response = text.as_str(),
+                "Unexpected response from server"
+            );
+        }
+        result.into_client_result()
+    }};
+}
+
 // #[cfg(test)]
 // mod test {
 //     use std::str::FromStr;
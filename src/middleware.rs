@@ -1,7 +1,12 @@
+use std::{fmt, future::Future, pin::Pin, sync::Arc, task::Poll};
+
 use ethers::{
-    providers::{FromErr, Middleware},
+    providers::{FromErr, Middleware, PendingTransaction, ProviderError},
     signers::Signer,
-    types::{transaction::eip2718::TypedTransaction, Address, Signature},
+    types::{
+        transaction::eip2718::TypedTransaction, Address, BlockId, NameOrAddress, Signature,
+        TransactionReceipt, TransactionRequest, H256, U256,
+    },
 };
 use tokio::{
     sync::{RwLock, RwLockReadGuard},
@@ -10,10 +15,20 @@ use tokio::{
 
 use crate::{
     client::{SigningClient, SigningClientError},
+    contracts::{exec_transaction_calldata, execution_success, pack_signatures},
+    gas::{self, EstimationError},
+    offline::{BundleError, ProposalBundle, ProposalStore},
+    policy::{PolicyEngine, SpendRecord},
     rpc::{
         common::Operations,
-        propose::{MetaTransactionData, ProposeRequest, SafeGasConfig, SafeTransactionData},
+        info::SafeInfoResponse,
+        propose::{
+            ConversionError, MetaTransactionData, ProposalOrigin, ProposeRequest, ResolveError,
+            SafeGasConfig, SafeTransactionData,
+        },
     },
+    safety::AllowedHazards,
+    watch::WatchConfig,
     ClientError,
 };
 
@@ -33,6 +48,20 @@ where
     /// Incomplete tx details, does not specify to
     #[error("Transaction must specify to address")]
     MissingTo,
+    /// Local gas estimation error
+    #[error("{0}")]
+    EstimationError(EstimationError<M>),
+    /// Thrown when the provider errors while awaiting a transaction
+    #[error("{0}")]
+    ProviderError(ProviderError),
+    /// Execution can only be awaited for proposals submitted to the service
+    /// or executed directly
+    #[error("Cannot await execution of proposals neither submitted to the service nor executed")]
+    NotSubmitted,
+    /// The service reports the transaction executed, but not by which
+    /// ethereum transaction
+    #[error("Safe transaction {0:?} executed without execution tx hash")]
+    MissingExecutionHash(H256),
 }
 
 impl<M, S> From<ClientError> for SafeMiddlewareError<M, S>
@@ -55,16 +84,48 @@ where
     }
 }
 
+/// How the middleware sets the gas fields of Safe transactions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GasPolicy {
+    /// Use [`SafeMiddlewareConfig::gas`] as-is
+    #[default]
+    Static,
+    /// Estimate `safeTxGas` via the service. If [`SafeMiddlewareConfig::gas`]
+    /// sets a nonzero `gas_price`, the executor is refunded in its
+    /// `gas_token` to its `refund_receiver`, and `baseGas` is estimated for
+    /// the Safe's threshold
+    Estimate,
+    /// As [`GasPolicy::Estimate`], but simulate the inner call via the inner
+    /// middleware instead of the service. See [`gas::GasEstimator`]
+    Local,
+}
+
 /// Safe Middleware configuration
 #[derive(Debug, Clone, Copy)]
 pub struct SafeMiddlewareConfig {
     /// True if the middleware should submit to the service, false if it should
-    /// only cache proposals
+    /// only cache proposals. See [`SafeMiddleware::with_store`] to persist
+    /// cached proposals
     pub submit_to_service: bool,
     /// Operation to use if no operation is specifie
     pub default_operation: Operations,
     /// Gas refund configuration to use in txns
     pub gas: SafeGasConfig,
+    /// Whether to estimate gas fields, or use `gas` as-is
+    pub gas_policy: GasPolicy,
+    /// Polling configuration used when awaiting execution of sent txns
+    pub watch: WatchConfig,
+    /// True if sent txns should be executed directly via `execTransaction`
+    /// when the local signer alone meets the Safe's threshold (e.g. 1-of-N
+    /// Safes). Gas is paid by the inner middleware's sender. When
+    /// `submit_to_service` is also set, txns are recorded with the service
+    /// for history before execution
+    pub auto_execute: bool,
+    /// True if `estimate_gas` & `fill_transaction` should estimate the
+    /// executing `execTransaction` call. Each call then fetches the Safe's
+    /// info, and the [`GasPolicy`] estimate. Otherwise they are passed to
+    /// the inner middleware
+    pub estimate_exec_gas: bool,
 }
 
 impl Default for SafeMiddlewareConfig {
@@ -73,10 +134,79 @@ impl Default for SafeMiddlewareConfig {
             submit_to_service: true,
             default_operation: Operations::Call,
             gas: Default::default(),
+            gas_policy: Default::default(),
+            watch: Default::default(),
+            auto_execute: false,
+            estimate_exec_gas: false,
         }
     }
 }
 
+/// The outcome of a Safe transaction executed on-chain
+#[derive(Debug, Clone)]
+pub struct SafeTxReceipt {
+    /// The Safe's internal tx hash
+    pub safe_tx_hash: H256,
+    /// Hash of the ethereum transaction that executed the Safe transaction
+    pub transaction_hash: H256,
+    /// `false` if the inner call reverted. The Safe nonce is consumed
+    /// regardless
+    pub success: bool,
+    /// Receipt of the executing ethereum transaction, if available from the
+    /// provider
+    pub receipt: Option<TransactionReceipt>,
+}
+
+type ExecutionFuture<'a, M, S> =
+    Pin<Box<dyn Future<Output = Result<SafeTxReceipt, SafeMiddlewareError<M, S>>> + Send + 'a>>;
+
+/// A proposed Safe transaction. Resolves once the transaction has been
+/// confirmed by enough owners and executed on-chain
+#[must_use = "futures do nothing unless polled"]
+pub struct SafePendingTransaction<'a, M, S>
+where
+    M: Middleware,
+    S: Signer,
+{
+    safe_tx_hash: H256,
+    execution: ExecutionFuture<'a, M, S>,
+}
+
+impl<'a, M, S> SafePendingTransaction<'a, M, S>
+where
+    M: Middleware,
+    S: Signer,
+{
+    /// The Safe's internal tx hash of the proposal
+    pub fn safe_tx_hash(&self) -> H256 {
+        self.safe_tx_hash
+    }
+}
+
+impl<'a, M, S> fmt::Debug for SafePendingTransaction<'a, M, S>
+where
+    M: Middleware,
+    S: Signer,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SafePendingTransaction")
+            .field("safe_tx_hash", &self.safe_tx_hash)
+            .finish()
+    }
+}
+
+impl<'a, M, S> Future for SafePendingTransaction<'a, M, S>
+where
+    M: Middleware,
+    S: Signer,
+{
+    type Output = Result<SafeTxReceipt, SafeMiddlewareError<M, S>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        self.execution.as_mut().poll(cx)
+    }
+}
+
 /// Safe middleware
 #[derive(Debug)]
 pub struct SafeMiddleware<M, S> {
@@ -85,6 +215,7 @@ pub struct SafeMiddleware<M, S> {
     client: SigningClient<S>,
     config: SafeMiddlewareConfig,
     proposals: RwLock<Vec<ProposeRequest>>,
+    store: Option<ProposalStore>,
 }
 
 impl<M, S> std::ops::Deref for SafeMiddleware<M, S> {
@@ -102,6 +233,76 @@ impl<M, S> SafeMiddleware<M, S> {
     pub async fn proposals(&self) -> RwLockReadGuard<'_, Vec<ProposeRequest>> {
         self.proposals.read().await
     }
+
+    /// Replace the middleware configuration
+    pub fn with_config(mut self, config: SafeMiddlewareConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Return the middleware configuration
+    pub fn config(&self) -> &SafeMiddlewareConfig {
+        &self.config
+    }
+
+    /// Persist all proposals to a local store, in addition to caching them
+    /// in memory. Stored proposals survive process exit, and may be exported
+    /// via [`SafeMiddleware::export_bundle`]
+    pub fn with_store(mut self, store: ProposalStore) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Return the local store, if any
+    pub fn store(&self) -> Option<&ProposalStore> {
+        self.store.as_ref()
+    }
+
+    /// Enforce a policy on transactions before they are signed. Rejected
+    /// transactions error with [`ClientError::PolicyViolation`]
+    pub fn with_policy(mut self, policy: Arc<PolicyEngine>) -> Self {
+        self.client.policy = Some(policy);
+        self
+    }
+
+    /// Allow signing transactions with these hazards. Transactions with
+    /// hazards error with [`ClientError::UnsafeTransaction`] by default
+    pub fn allow_hazards(mut self, allowed: AllowedHazards) -> Self {
+        self.client.allowed_hazards = allowed;
+        self
+    }
+
+    /// Attach origin metadata to proposals, so co-signers see who or what
+    /// proposed them
+    pub fn with_origin(mut self, origin: ProposalOrigin) -> Self {
+        self.client.origin = Some(origin);
+        self
+    }
+}
+
+impl<M, S> SafeMiddleware<M, S>
+where
+    S: Signer,
+{
+    /// Export proposals as a signed bundle, for co-signing and submission
+    /// elsewhere. Includes every proposal in the local store if one is
+    /// configured, otherwise those cached in memory
+    pub async fn export_bundle(&self) -> Result<ProposalBundle, BundleError> {
+        let chain_id = self.client.signer.chain_id();
+        if let Some(store) = &self.store {
+            if let Some(bundle) = store.load().await? {
+                return Ok(bundle);
+            }
+        }
+        let mut bundle = ProposalBundle::new(self.safe_address, chain_id);
+        self.proposals
+            .read()
+            .await
+            .iter()
+            .cloned()
+            .for_each(|p| bundle.insert(p));
+        Ok(bundle)
+    }
 }
 
 impl<M, S> SafeMiddleware<M, S>
@@ -130,32 +331,263 @@ where
             client,
             config: Default::default(),
             proposals: RwLock::new(Default::default()),
+            store: None,
         })
     }
 
+    #[allow(clippy::needless_lifetimes)]
     async fn to_meta_tx<'a>(
         &self,
         tx: &'a TypedTransaction,
     ) -> Result<MetaTransactionData, SafeMiddlewareError<M, S>> {
-        let to = tx.to().ok_or(SafeMiddlewareError::MissingTo)?;
-        let to = match to {
-            ethers::types::NameOrAddress::Name(name) => self
-                .inner()
-                .resolve_name(name)
+        MetaTransactionData::resolve(tx, &self.inner)
+            .await
+            .map_err(|e| match e {
+                ResolveError::ConversionError(ConversionError::MissingTo) => {
+                    SafeMiddlewareError::MissingTo
+                }
+                ResolveError::ConversionError(e) => ClientError::from(e).into(),
+                ResolveError::MiddlewareError(e) => SafeMiddlewareError::MiddlewareError(e),
+            })
+    }
+
+    /// Convert to an unsigned Safe transaction at the Safe's current nonce,
+    /// with gas fields set according to the configured [`GasPolicy`]
+    async fn to_safe_tx(
+        &self,
+        tx: &TypedTransaction,
+    ) -> Result<(SafeTransactionData, SafeInfoResponse, U256), SafeMiddlewareError<M, S>> {
+        // in order to use shortcutting try_join, we have to have all error
+        // types be the same. So 1 future needs to be wrapped & mapped
+
+        let (mut core, chain_id, info) =
+            try_join!(self.to_meta_tx(tx), self.get_chainid(), async {
+                Ok(self.client.safe_info(self.safe_address).await?)
+            },)?;
+
+        let SafeMiddlewareConfig {
+            default_operation,
+            gas,
+            gas_policy,
+            ..
+        } = self.config;
+
+        // override from config if necessary
+        if core.operation.is_none() {
+            core.operation = Some(default_operation)
+        }
+
+        let mut proposal = SafeTransactionData {
+            core,
+            gas,
+            nonce: info.nonce,
+        };
+
+        let safe_tx_gas = match gas_policy {
+            GasPolicy::Static => None,
+            GasPolicy::Estimate => Some(
+                self.client
+                    .estimate_gas(self.safe_address, &proposal)
+                    .await?,
+            ),
+            GasPolicy::Local => Some(
+                gas::estimate_safe_tx_gas(&self.inner, self.safe_address, &proposal.core)
+                    .await
+                    .map_err(SafeMiddlewareError::EstimationError)?
+                    .into(),
+            ),
+        };
+        if let Some(safe_tx_gas) = safe_tx_gas {
+            proposal.gas.safe_tx_gas = safe_tx_gas;
+            // base gas is only used to compute the refund
+            if !proposal.gas.gas_price.is_zero() {
+                proposal.gas.base_gas = gas::estimate_base_gas(&proposal, info.threshold).into();
+            }
+        }
+
+        Ok((proposal, info, chain_id))
+    }
+
+    /// Convert to a Safe transaction, sign, and cache. Also persists to the
+    /// local store, if any. Returns the policy's spend record, to be rolled
+    /// back if the proposal is not submitted
+    async fn sign_typed_tx(
+        &self,
+        tx: &TypedTransaction,
+    ) -> Result<(ProposeRequest, SafeInfoResponse, Option<SpendRecord>), SafeMiddlewareError<M, S>>
+    {
+        let (proposal, info, chain_id) = self.to_safe_tx(tx).await?;
+        let record = self
+            .client
+            .check_before_signing(self.safe_address, &proposal)?;
+
+        let result = self.sign_and_store(proposal, chain_id.low_u64()).await;
+        if result.is_err() {
+            self.client.rollback_spend(record);
+        }
+        Ok((result?, info, record))
+    }
+
+    async fn sign_and_store(
+        &self,
+        proposal: SafeTransactionData,
+        chain_id: u64,
+    ) -> Result<ProposeRequest, SafeMiddlewareError<M, S>> {
+        let proposal = proposal
+            .into_request(&self.client.signer, self.safe_address, chain_id)
+            .await
+            .map_err(SigningClientError::<S>::SignerError)?;
+        let proposal = self.client.attach_origin(proposal);
+
+        // guard dropped immediately on use
+        self.proposals.write().await.push(proposal.clone());
+        if let Some(store) = &self.store {
+            store
+                .push(self.safe_address, chain_id, proposal.clone())
                 .await
-                .map_err(SafeMiddlewareError::MiddlewareError)?,
-            ethers::types::NameOrAddress::Address(addr) => *addr,
+                .map_err(ClientError::from)?;
         }
-        .into();
+        Ok(proposal)
+    }
 
-        let value = tx.value().copied().unwrap_or_default();
-        let data = tx.data().cloned();
+    /// Submit a signed proposal, rolling back the policy's spend record if
+    /// the service rejects it
+    async fn submit_signed(
+        &self,
+        proposal: ProposeRequest,
+        info: &SafeInfoResponse,
+        record: Option<SpendRecord>,
+    ) -> Result<(), SafeMiddlewareError<M, S>> {
+        if let Err(e) = self
+            .client
+            .submit_proposal_with_owners(proposal, self.safe_address, &info.owners)
+            .await
+        {
+            self.client.rollback_spend(record);
+            return Err(e.into());
+        }
+        Ok(())
+    }
 
-        Ok(MetaTransactionData {
-            to,
-            value: value.low_u64(),
-            data,
-            operation: None,
+    /// Convert to a Safe transaction, sign, cache, and submit to the service
+    /// if configured to
+    async fn propose_typed_tx(
+        &self,
+        tx: &TypedTransaction,
+    ) -> Result<ProposeRequest, SafeMiddlewareError<M, S>> {
+        let (proposal, info, record) = self.sign_typed_tx(tx).await?;
+        if self.config.submit_to_service {
+            self.submit_signed(proposal.clone(), &info, record).await?;
+        }
+        Ok(proposal)
+    }
+
+    /// True if auto-execution is enabled, and the local signer alone meets
+    /// the Safe's threshold
+    fn can_auto_execute(&self, info: &SafeInfoResponse) -> bool {
+        self.config.auto_execute
+            && info.threshold == 1
+            && info.owners.contains(&self.client.signer.address())
+    }
+
+    /// Execute a signed proposal by calling `execTransaction` on the Safe via
+    /// the inner middleware. The inner middleware pays for gas
+    async fn execute_proposal(
+        &self,
+        proposal: &ProposeRequest,
+    ) -> Result<PendingTransaction<'_, M::Provider>, SafeMiddlewareError<M, S>> {
+        let data = exec_transaction_calldata(
+            proposal.tx(),
+            pack_signatures(std::iter::once(proposal.signature())),
+        );
+        let tx = TransactionRequest::new().to(self.safe_address).data(data);
+        self.inner
+            .send_transaction(tx, None)
+            .await
+            .map_err(SafeMiddlewareError::MiddlewareError)
+    }
+
+    /// Propose a transaction to the service, returning a pending transaction
+    /// that resolves once the Safe transaction is executed on-chain.
+    ///
+    /// If [`SafeMiddlewareConfig::auto_execute`] is set and the local signer
+    /// alone meets the threshold, the transaction is instead executed
+    /// directly, and recorded with the service only if
+    /// [`SafeMiddlewareConfig::submit_to_service`] is set.
+    ///
+    /// Errors if the transaction can be neither executed directly nor
+    /// submitted to the service
+    pub async fn send_safe_transaction<T: Into<TypedTransaction> + Send + Sync>(
+        &self,
+        tx: T,
+    ) -> Result<SafePendingTransaction<'_, M, S>, SafeMiddlewareError<M, S>> {
+        let SafeMiddlewareConfig {
+            submit_to_service,
+            auto_execute,
+            watch,
+            ..
+        } = self.config;
+        if !submit_to_service && !auto_execute {
+            return Err(SafeMiddlewareError::NotSubmitted);
+        }
+
+        let (proposal, info, record) = self.sign_typed_tx(&tx.into()).await?;
+        let safe_tx_hash = proposal.safe_tx_hash();
+
+        if self.can_auto_execute(&info) {
+            // record before executing, as the service rejects proposals for
+            // used nonces
+            if submit_to_service {
+                self.submit_signed(proposal.clone(), &info, record).await?;
+            }
+            let pending = self.execute_proposal(&proposal).await?;
+            let transaction_hash = pending.tx_hash();
+
+            let execution = Box::pin(async move {
+                let receipt = pending.await.map_err(SafeMiddlewareError::ProviderError)?;
+                let success = receipt
+                    .as_ref()
+                    .and_then(|r| execution_success(r, self.safe_address, safe_tx_hash))
+                    .unwrap_or(false);
+                Ok(SafeTxReceipt {
+                    safe_tx_hash,
+                    transaction_hash,
+                    success,
+                    receipt,
+                })
+            });
+            return Ok(SafePendingTransaction {
+                safe_tx_hash,
+                execution,
+            });
+        }
+
+        if !submit_to_service {
+            return Err(SafeMiddlewareError::NotSubmitted);
+        }
+        self.submit_signed(proposal, &info, record).await?;
+
+        let execution = Box::pin(async move {
+            let executed = self.client.wait_for_execution(safe_tx_hash, watch).await?;
+            let transaction_hash = executed
+                .transaction_hash
+                .ok_or(SafeMiddlewareError::MissingExecutionHash(safe_tx_hash))?;
+            let receipt = self
+                .inner
+                .get_transaction_receipt(transaction_hash)
+                .await
+                .map_err(SafeMiddlewareError::MiddlewareError)?;
+            Ok(SafeTxReceipt {
+                safe_tx_hash,
+                transaction_hash,
+                success: executed.is_successful.unwrap_or(true),
+                receipt,
+            })
+        });
+
+        Ok(SafePendingTransaction {
+            safe_tx_hash,
+            execution,
         })
     }
 }
@@ -176,51 +608,240 @@ where
         &self.inner
     }
 
+    /// Propose a transaction to the service, and wait until the Safe
+    /// transaction is executed on-chain. Resolves to a pending transaction
+    /// for the executing ethereum transaction.
+    ///
+    /// Note that this waits for co-signers to confirm the proposal, erroring
+    /// with [`ClientError::Timeout`] once [`SafeMiddlewareConfig::watch`]
+    /// times out. Use [`SafeMiddleware::send_safe_transaction`] to access
+    /// the safe tx hash before execution
+    async fn send_transaction<T: Into<TypedTransaction> + Send + Sync>(
+        &self,
+        tx: T,
+        _block: Option<BlockId>,
+    ) -> Result<PendingTransaction<'_, Self::Provider>, Self::Error> {
+        let executed = self.send_safe_transaction(tx).await?.await?;
+        Ok(PendingTransaction::new(
+            executed.transaction_hash,
+            self.provider(),
+        ))
+    }
+
+    /// Fill a transaction as sent by the Safe: resolve ENS names, set `from`
+    /// to the Safe, and set `gas` to the estimated gas of the executing
+    /// `execTransaction` call if unset. Only if
+    /// [`SafeMiddlewareConfig::estimate_exec_gas`] is set, otherwise filled
+    /// by the inner middleware.
+    ///
+    /// Nonce and gas price are not filled, as they belong to the executing
+    /// ethereum transaction rather than the Safe transaction
+    async fn fill_transaction(
+        &self,
+        tx: &mut TypedTransaction,
+        block: Option<BlockId>,
+    ) -> Result<(), Self::Error> {
+        if !self.config.estimate_exec_gas {
+            return self
+                .inner
+                .fill_transaction(tx, block)
+                .await
+                .map_err(SafeMiddlewareError::MiddlewareError);
+        }
+        if let Some(NameOrAddress::Name(ens_name)) = tx.to() {
+            let addr = self
+                .inner
+                .resolve_name(ens_name)
+                .await
+                .map_err(SafeMiddlewareError::MiddlewareError)?;
+            tx.set_to(addr);
+        }
+        tx.set_from(self.safe_address);
+        if tx.gas().is_none() {
+            let gas = self.estimate_gas(tx, block).await?;
+            tx.set_gas(gas);
+        }
+        Ok(())
+    }
+
+    /// Estimate the gas of the `execTransaction` call executing the
+    /// transaction from the Safe, with signatures from `threshold` owners.
+    ///
+    /// If the configured [`GasPolicy`] does not set `safeTxGas`, the inner
+    /// call is estimated by the inner middleware as a call from the Safe.
+    /// Only if [`SafeMiddlewareConfig::estimate_exec_gas`] is set, otherwise
+    /// estimated by the inner middleware
+    async fn estimate_gas(
+        &self,
+        tx: &TypedTransaction,
+        block: Option<BlockId>,
+    ) -> Result<U256, Self::Error> {
+        if !self.config.estimate_exec_gas {
+            return self
+                .inner
+                .estimate_gas(tx, block)
+                .await
+                .map_err(SafeMiddlewareError::MiddlewareError);
+        }
+        let (safe_tx, info, _) = self.to_safe_tx(tx).await?;
+
+        let inner_gas = if !safe_tx.gas.safe_tx_gas.is_zero() {
+            gas::gas_to_u64(safe_tx.gas.safe_tx_gas)
+                .map_err(SafeMiddlewareError::EstimationError)?
+        } else {
+            let mut inner_tx = tx.clone();
+            inner_tx.set_from(self.safe_address);
+            let estimate = self
+                .inner
+                .estimate_gas(&inner_tx, block)
+                .await
+                .map_err(SafeMiddlewareError::MiddlewareError)?;
+            gas::inner_call_gas(
+                gas::gas_to_u64(estimate).map_err(SafeMiddlewareError::EstimationError)?,
+                safe_tx.core.data.as_deref().unwrap_or_default(),
+            )
+        };
+
+        Ok(gas::estimate_exec_gas(&safe_tx, info.threshold, inner_gas).into())
+    }
+
     /// Sign a transaction via RPC call
+    ///
+    /// Note: the returned signature is the owner's EIP-712 signature of the
+    /// Safe transaction, not a signature over the ethereum transaction
     async fn sign_transaction(
         &self,
         tx: &TypedTransaction,
         _from: Address,
     ) -> Result<Signature, Self::Error> {
-        // in order to use shortcutting try_join, we have to have all error
-        // types be the same. So 1 future needs to be wrapped & mapped
+        Ok(self.propose_typed_tx(tx).await?.signature().signature())
+    }
+}
 
-        let (mut core, chain_id, info) =
-            try_join!(self.to_meta_tx(tx), self.get_chainid(), async {
-                Ok(self.client.safe_info(self.safe_address).await?)
-            },)?;
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
 
-        let SafeMiddlewareConfig {
-            submit_to_service,
-            default_operation,
-            gas,
-        } = self.config;
+    use ethers::{
+        providers::{MockProvider, Provider},
+        signers::LocalWallet,
+    };
 
-        // override from config if necessary
-        if core.operation.is_none() {
-            core.operation = Some(default_operation)
+    use super::*;
+    use crate::rpc::{
+        mock::{page, safe_info, MockService},
+        msig_history::fixtures::msig_tx_json,
+    };
+
+    const SAFE: Address = Address::repeat_byte(0x5a);
+
+    fn signer() -> LocalWallet {
+        "1c3a7cdd2270579847aaec11680312cbf4d3c36886232b413ab6529593228ec2"
+            .parse::<LocalWallet>()
+            .unwrap()
+            .with_chain_id(1u64)
+    }
+
+    /// Serve a Safe at nonce 3 with threshold 2, so proposals await
+    /// co-signers. Its history has `at_nonce` at the pending nonce, and an
+    /// executed txn at an older nonce. Proposals are reported executed from
+    /// the `executed_after`th lookup on
+    fn service(
+        at_nonce: Vec<serde_json::Value>,
+        executed_after: usize,
+    ) -> impl Fn(&str, &str) -> (u16, serde_json::Value) + Send + Sync + 'static {
+        let owner = signer().address();
+        let lookups = AtomicUsize::new(0);
+        move |path, query| {
+            if path.ends_with("/multisig-transactions/") && query.is_empty() {
+                // proposal
+                (201, serde_json::Value::Null)
+            } else if path.ends_with("/multisig-transactions/") {
+                let older = msig_tx_json(SAFE, 1, H256::repeat_byte(1), true, &[owner]);
+                match query {
+                    "nonce=3" => (200, page(at_nonce.clone())),
+                    _ => (200, page(vec![older])),
+                }
+            } else if let Some(hash) = path.strip_prefix("/api/v1/multisig-transactions/") {
+                let hash: H256 = hash.trim_end_matches('/').parse().unwrap();
+                let executed = lookups.fetch_add(1, Ordering::SeqCst) + 1 >= executed_after;
+                let mut tx = msig_tx_json(SAFE, 3, hash, executed, &[owner]);
+                if executed {
+                    tx["transactionHash"] = serde_json::json!(H256::repeat_byte(0xee));
+                }
+                (200, tx)
+            } else {
+                (200, safe_info(SAFE, 3, 2, &[owner]))
+            }
         }
+    }
 
-        let proposal = SafeTransactionData {
-            core,
-            gas,
-            nonce: info.nonce,
+    fn middleware(
+        service: &MockService,
+    ) -> (
+        SafeMiddleware<Provider<MockProvider>, LocalWallet>,
+        MockProvider,
+    ) {
+        let (provider, mock) = Provider::mocked();
+        let middleware = SafeMiddleware {
+            safe_address: SAFE,
+            inner: provider,
+            client: service.client(1).with_signer(signer()),
+            config: SafeMiddlewareConfig {
+                watch: WatchConfig {
+                    poll_interval: Duration::from_millis(10),
+                    timeout: Some(Duration::from_secs(5)),
+                },
+                ..Default::default()
+            },
+            proposals: RwLock::new(Default::default()),
+            store: None,
         };
+        (middleware, mock)
+    }
 
-        let proposal = proposal
-            .into_request(&self.client.signer, self.safe_address, chain_id.low_u64())
-            .await
-            .map_err(SigningClientError::<S>::SignerError)?;
+    #[tokio::test]
+    async fn it_resolves_once_executed() {
+        // the proposal's own lookup, the watcher's first poll, then executed
+        let service = MockService::start(service(vec![], 3)).await;
+        let (middleware, mock) = middleware(&service);
+        // popped last-in first-out
+        mock.push(Option::<TransactionReceipt>::None).unwrap();
+        mock.push(U256::one()).unwrap();
 
-        // guard dropped immediately on use
-        self.proposals.write().await.push(proposal.clone());
+        let tx = TransactionRequest::new()
+            .to(Address::repeat_byte(0x22))
+            .value(1);
+        let pending = middleware.send_safe_transaction(tx).await.unwrap();
+        let safe_tx_hash = pending.safe_tx_hash();
+        let receipt = pending.await.unwrap();
+
+        assert_eq!(receipt.safe_tx_hash, safe_tx_hash);
+        assert_eq!(receipt.transaction_hash, H256::repeat_byte(0xee));
+        assert!(receipt.success);
+        assert_eq!(middleware.proposals().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn it_errors_if_replaced() {
+        let owner = signer().address();
+        let replacement = msig_tx_json(SAFE, 3, H256::repeat_byte(3), true, &[owner]);
+        let service = MockService::start(service(vec![replacement], usize::MAX)).await;
+        let (middleware, mock) = middleware(&service);
+        mock.push(U256::one()).unwrap();
 
-        let signature = proposal.signature.signature();
-        if submit_to_service {
-            self.client
-                .submit_proposal(proposal, self.safe_address)
-                .await?;
+        let tx = TransactionRequest::new()
+            .to(Address::repeat_byte(0x22))
+            .value(1);
+        let pending = middleware.send_safe_transaction(tx).await.unwrap();
+        match pending.await {
+            Err(SafeMiddlewareError::SigningClientError(SigningClientError::ClientError(
+                ClientError::Replaced { by, .. },
+            ))) => assert_eq!(by, H256::repeat_byte(3)),
+            other => panic!("expected replacement, got {other:?}"),
         }
-        Ok(signature)
     }
 }
@@ -0,0 +1,594 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use ethers::{
+    signers::Signer,
+    types::{Address, H256},
+};
+use once_cell::sync::Lazy;
+use tokio::{fs, sync::Mutex};
+
+use crate::{
+    client::{ClientResult, SigningClientError, SigningClientResult},
+    policy::{PolicyEngine, SpendRecord},
+    rpc::{
+        common::ChecksumAddress,
+        msig_history::MsigTxResponse,
+        propose::{ProposeRequest, ProposeSignature, SafeTransactionData},
+    },
+    safety::{self, AllowedHazards},
+    ClientError, SafeClient, SigningClient,
+};
+
+/// Current version of the bundle format
+pub const BUNDLE_VERSION: u8 = 1;
+
+/// Errors produced when handling offline bundles
+#[derive(Debug, thiserror::Error)]
+pub enum BundleError {
+    /// Filesystem error
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    /// Serde Json deser Error
+    #[error("{0}")]
+    SerdeError(#[from] serde_json::Error),
+    /// Bundle was produced by an incompatible version
+    #[error("Unsupported bundle version {0}")]
+    UnsupportedVersion(u8),
+    /// Stored safe tx hash does not match the transaction details
+    #[error("Safe tx hash mismatch. Expected {expected:?}, computed {computed:?}")]
+    HashMismatch {
+        /// The hash stored in the bundle
+        expected: H256,
+        /// The hash computed from the transaction details
+        computed: H256,
+    },
+    /// A signature does not recover to its stated sender
+    #[error("Invalid signature by {sender:?} on {safe_tx_hash:?}")]
+    InvalidSignature {
+        /// The safe tx hash of the proposal
+        safe_tx_hash: H256,
+        /// The stated sender of the signature
+        sender: Address,
+    },
+    /// Bundles for different Safes or chains cannot be combined
+    #[error("Bundle is for Safe {found:?} on chain {found_chain_id}. Expected Safe {expected:?} on chain {expected_chain_id}")]
+    WrongSafe {
+        /// The expected Safe
+        expected: Address,
+        /// The expected chain id
+        expected_chain_id: u64,
+        /// The bundle's Safe
+        found: Address,
+        /// The bundle's chain id
+        found_chain_id: u64,
+    },
+}
+
+/// A Safe transaction and the owner signatures collected for it
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BundledProposal {
+    /// The Safe's internal tx hash
+    pub safe_tx_hash: H256,
+    /// The transaction details
+    pub tx: SafeTransactionData,
+    /// Signatures collected so far. The first is used as the proposal
+    /// signature when submitting
+    pub signatures: Vec<ProposeSignature>,
+}
+
+impl From<ProposeRequest> for BundledProposal {
+    fn from(req: ProposeRequest) -> Self {
+        Self {
+            safe_tx_hash: req.contract_transaction_hash,
+            tx: req.tx,
+            signatures: vec![req.signature],
+        }
+    }
+}
+
+impl BundledProposal {
+    /// Iterate over the addresses that have signed this proposal
+    pub fn signers(&self) -> impl Iterator<Item = Address> + '_ {
+        self.signatures.iter().map(|s| s.sender().into())
+    }
+
+    /// Check that the stored hash matches the transaction, and that every
+    /// signature recovers to its stated sender
+    pub fn verify(&self, safe_address: Address, chain_id: u64) -> Result<(), BundleError> {
+        let computed = self.tx.safe_tx_hash(safe_address, chain_id);
+        if computed != self.safe_tx_hash {
+            return Err(BundleError::HashMismatch {
+                expected: self.safe_tx_hash,
+                computed,
+            });
+        }
+        for sig in self.signatures.iter() {
+            let sender: Address = sig.sender().into();
+            if sig.signature().recover(self.safe_tx_hash).ok() != Some(sender) {
+                return Err(BundleError::InvalidSignature {
+                    safe_tx_hash: self.safe_tx_hash,
+                    sender,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Add signatures from `other`, skipping signers already present
+    fn merge(&mut self, other: BundledProposal) {
+        for sig in other.signatures {
+            if !self.signers().any(|s| s == *sig.sender()) {
+                self.signatures.push(sig);
+            }
+        }
+    }
+
+    /// The proposal request, using the first signature. `None` if there are
+    /// no signatures
+    pub fn to_request(&self) -> Option<ProposeRequest> {
+        Some(ProposeRequest {
+            tx: self.tx.clone(),
+            contract_transaction_hash: self.safe_tx_hash,
+            signature: self.signatures.first()?.clone(),
+        })
+    }
+}
+
+/// A portable set of signed proposals for one Safe. Serialized as JSON, it
+/// may be moved between machines so that air-gapped owners can co-sign
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProposalBundle {
+    /// Bundle format version
+    pub version: u8,
+    /// Chain id of the Safe
+    pub chain_id: u64,
+    /// The Safe's address
+    pub safe_address: ChecksumAddress,
+    /// The proposals, in insertion order
+    pub proposals: Vec<BundledProposal>,
+}
+
+impl ProposalBundle {
+    /// Instantiate an empty bundle
+    pub fn new(safe_address: Address, chain_id: u64) -> Self {
+        Self {
+            version: BUNDLE_VERSION,
+            chain_id,
+            safe_address: safe_address.into(),
+            proposals: vec![],
+        }
+    }
+
+    /// Parse and verify a bundle from JSON
+    pub fn from_json(json: &str) -> Result<Self, BundleError> {
+        let bundle: Self = serde_json::from_str(json)?;
+        bundle.verify()?;
+        Ok(bundle)
+    }
+
+    /// Serialize the bundle to pretty-printed JSON
+    pub fn to_json(&self) -> Result<String, BundleError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Check the version, and verify every proposal. See
+    /// [`BundledProposal::verify`]
+    pub fn verify(&self) -> Result<(), BundleError> {
+        if self.version != BUNDLE_VERSION {
+            return Err(BundleError::UnsupportedVersion(self.version));
+        }
+        self.proposals
+            .iter()
+            .try_for_each(|p| p.verify(*self.safe_address, self.chain_id))
+    }
+
+    /// Check this bundle is for the expected Safe & chain
+    fn check_same_safe(&self, safe_address: Address, chain_id: u64) -> Result<(), BundleError> {
+        if *self.safe_address != safe_address || self.chain_id != chain_id {
+            return Err(BundleError::WrongSafe {
+                expected: safe_address,
+                expected_chain_id: chain_id,
+                found: *self.safe_address,
+                found_chain_id: self.chain_id,
+            });
+        }
+        Ok(())
+    }
+
+    /// Insert a proposal. If a proposal with the same hash exists, its
+    /// signatures are combined
+    pub fn insert(&mut self, proposal: impl Into<BundledProposal>) {
+        let proposal = proposal.into();
+        match self
+            .proposals
+            .iter_mut()
+            .find(|p| p.safe_tx_hash == proposal.safe_tx_hash)
+        {
+            Some(existing) => existing.merge(proposal),
+            None => self.proposals.push(proposal),
+        }
+    }
+
+    /// Combine another bundle for the same Safe into this one
+    pub fn merge(&mut self, other: ProposalBundle) -> Result<(), BundleError> {
+        other.check_same_safe(*self.safe_address, self.chain_id)?;
+        other.proposals.into_iter().for_each(|p| self.insert(p));
+        Ok(())
+    }
+
+    /// Add the signer's signature to every proposal it has not yet signed.
+    /// Refuses proposals with any hazard, see [`ProposalBundle::cosign_with`]
+    pub async fn cosign<S: Signer>(&mut self, signer: &S) -> SigningClientResult<(), S> {
+        self.cosign_with(signer, &AllowedHazards::default(), None)
+            .await
+    }
+
+    /// Add the signer's signature to every proposal it has not yet signed.
+    ///
+    /// First checks every unsigned proposal for hazards that are not
+    /// `allowed`, then evaluates the policy, if any, recording transfers
+    /// toward daily limits. Errors with [`ClientError::UnsafeTransaction`] or
+    /// [`ClientError::PolicyViolation`] without signing anything if any
+    /// proposal is refused
+    pub async fn cosign_with<S: Signer>(
+        &mut self,
+        signer: &S,
+        allowed: &AllowedHazards,
+        policy: Option<&PolicyEngine>,
+    ) -> SigningClientResult<(), S> {
+        let signer_address = signer.address();
+        let safe_address = *self.safe_address;
+        let unsigned: Vec<usize> = self
+            .proposals
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| !p.signers().any(|s| s == signer_address))
+            .map(|(i, _)| i)
+            .collect();
+
+        for &i in unsigned.iter() {
+            safety::check_hazards(safe_address, signer_address, &self.proposals[i].tx, allowed)
+                .map_err(ClientError::from)?;
+        }
+
+        let mut records = vec![];
+        let rollback = |records: Vec<SpendRecord>| {
+            if let Some(policy) = policy {
+                records.into_iter().for_each(|r| policy.rollback(r));
+            }
+        };
+        if let Some(policy) = policy {
+            for &i in unsigned.iter() {
+                match policy.check_and_record(safe_address, &self.proposals[i].tx) {
+                    Ok(record) => records.push(record),
+                    Err(e) => {
+                        rollback(records);
+                        return Err(ClientError::from(e).into());
+                    }
+                }
+            }
+        }
+
+        let mut signatures = Vec::with_capacity(unsigned.len());
+        for &i in unsigned.iter() {
+            match self.proposals[i]
+                .tx
+                .sign(signer, safe_address, self.chain_id)
+                .await
+            {
+                Ok(signature) => signatures.push(signature),
+                Err(e) => {
+                    rollback(records);
+                    return Err(SigningClientError::SignerError(e));
+                }
+            }
+        }
+        for (i, signature) in unsigned.into_iter().zip(signatures) {
+            self.proposals[i].signatures.push(signature);
+        }
+        Ok(())
+    }
+}
+
+/// File-backed storage of proposals, as a [`ProposalBundle`] JSON file.
+///
+/// Writes replace the file atomically, so a crash never leaves a partially
+/// written store. Stores at the same path share a lock, so concurrent pushes
+/// within the process never overwrite each other
+#[derive(Debug, Clone)]
+pub struct ProposalStore {
+    path: PathBuf,
+    lock: Arc<Mutex<()>>,
+}
+
+/// Locks of the stores in use, by path
+static STORE_LOCKS: Lazy<std::sync::Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>> =
+    Lazy::new(Default::default);
+
+impl ProposalStore {
+    /// Instantiate a store at `path`. The file is created on first write
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let lock = STORE_LOCKS
+            .lock()
+            .expect("poisoned")
+            .entry(path.clone())
+            .or_default()
+            .clone();
+        Self { path, lock }
+    }
+
+    /// The path of the backing file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load the stored bundle. `None` if nothing has been stored yet
+    pub async fn load(&self) -> Result<Option<ProposalBundle>, BundleError> {
+        let _guard = self.lock.lock().await;
+        self.read().await
+    }
+
+    /// Replace the stored bundle
+    pub async fn save(&self, bundle: &ProposalBundle) -> Result<(), BundleError> {
+        let _guard = self.lock.lock().await;
+        self.write(bundle).await
+    }
+
+    /// Add a proposal to the stored bundle, creating it if necessary
+    pub async fn push(
+        &self,
+        safe_address: Address,
+        chain_id: u64,
+        proposal: impl Into<BundledProposal>,
+    ) -> Result<ProposalBundle, BundleError> {
+        let _guard = self.lock.lock().await;
+        let mut bundle = match self.read().await? {
+            Some(bundle) => {
+                bundle.check_same_safe(safe_address, chain_id)?;
+                bundle
+            }
+            None => ProposalBundle::new(safe_address, chain_id),
+        };
+        bundle.insert(proposal);
+        self.write(&bundle).await?;
+        Ok(bundle)
+    }
+
+    async fn read(&self) -> Result<Option<ProposalBundle>, BundleError> {
+        match fs::read_to_string(&self.path).await {
+            Ok(json) => ProposalBundle::from_json(&json).map(Some),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn write(&self, bundle: &ProposalBundle) -> Result<(), BundleError> {
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, bundle.to_json()?).await?;
+        fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+}
+
+impl<S: Signer> SigningClient<S> {
+    /// Add the signer's signature to every proposal in the bundle it has not
+    /// yet signed, refusing hazards the client does not allow, and enforcing
+    /// its policy, if any. See [`ProposalBundle::cosign_with`]
+    pub async fn cosign_bundle(&self, bundle: &mut ProposalBundle) -> SigningClientResult<(), S> {
+        bundle
+            .cosign_with(&self.signer, &self.allowed_hazards, self.policy.as_deref())
+            .await
+    }
+}
+
+impl SafeClient {
+    /// Submit every proposal in a bundle, along with all collected
+    /// signatures. Proposals already known to the service are not resubmitted,
+    /// but any new signatures are added as confirmations
+    #[tracing::instrument(skip(self, bundle))]
+    pub async fn submit_bundle(
+        &self,
+        bundle: &ProposalBundle,
+    ) -> ClientResult<Vec<MsigTxResponse>> {
+        bundle.check_same_safe(*bundle.safe_address, self.network().chain_id)?;
+        bundle.verify()?;
+
+        let owners = self.safe_info(*bundle.safe_address).await?.owners;
+        let mut results = Vec::with_capacity(bundle.proposals.len());
+        for proposal in bundle.proposals.iter() {
+            let existing = match self.transaction_info(proposal.safe_tx_hash).await {
+                Ok(tx) => Some(tx),
+                Err(ClientError::ServerErrorCode(reqwest::StatusCode::NOT_FOUND)) => None,
+                Err(e) => return Err(e),
+            };
+
+            let mut confirmed: Vec<Address> = match existing {
+                Some(tx) => tx.confirmations.iter().map(|c| c.owner).collect(),
+                None => match proposal.to_request() {
+                    Some(req) => {
+                        let sender = *req.signature().sender();
+                        self.submit_signed_proposal_with_owners(req, *bundle.safe_address, &owners)
+                            .await?;
+                        vec![sender]
+                    }
+                    None => continue,
+                },
+            };
+
+            for sig in proposal.signatures.iter() {
+                let sender: Address = sig.sender().into();
+                if confirmed.contains(&sender) {
+                    continue;
+                }
+                self.submit_confirmation(proposal.safe_tx_hash, sig.signature())
+                    .await?;
+                confirmed.push(sender);
+            }
+            results.push(self.transaction_info(proposal.safe_tx_hash).await?);
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ethers::signers::LocalWallet;
+
+    use super::*;
+    use crate::rpc::propose::MetaTransactionData;
+
+    const SAFE: &str = "0x38CD8Fa77ECEB4b1edB856Ed27aac6A6c6Dc88ca";
+
+    #[tokio::test]
+    async fn it_roundtrips_and_cosigns() {
+        let safe: Address = SAFE.parse().unwrap();
+        let proposer: LocalWallet =
+            "1c3a7cdd2270579847aaec11680312cbf4d3c36886232b413ab6529593228ec2"
+                .parse()
+                .unwrap();
+        let cosigner: LocalWallet =
+            "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+                .parse()
+                .unwrap();
+
+        let tx = SafeTransactionData {
+            core: MetaTransactionData {
+                to: proposer.address().into(),
+                value: 1.into(),
+                data: None,
+                operation: None,
+            },
+            gas: Default::default(),
+            nonce: 3,
+        };
+        let req = tx.into_request(&proposer, safe, 5).await.unwrap();
+
+        let mut bundle = ProposalBundle::new(safe, 5);
+        bundle.insert(req);
+
+        let mut imported = ProposalBundle::from_json(&bundle.to_json().unwrap()).unwrap();
+        imported.cosign(&cosigner).await.unwrap();
+        // signing twice is a no-op
+        imported.cosign(&cosigner).await.unwrap();
+        imported.verify().unwrap();
+        assert_eq!(
+            imported.proposals[0].signers().collect::<Vec<_>>(),
+            vec![proposer.address(), cosigner.address()]
+        );
+
+        bundle.merge(imported.clone()).unwrap();
+        assert_eq!(bundle.proposals[0].signatures.len(), 2);
+
+        imported.proposals[0].tx.nonce = 4;
+        assert!(matches!(
+            imported.verify(),
+            Err(BundleError::HashMismatch { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_checks_before_cosigning() {
+        let safe: Address = SAFE.parse().unwrap();
+        let proposer: LocalWallet =
+            "1c3a7cdd2270579847aaec11680312cbf4d3c36886232b413ab6529593228ec2"
+                .parse()
+                .unwrap();
+        let cosigner: LocalWallet =
+            "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+                .parse()
+                .unwrap();
+
+        let tx = SafeTransactionData {
+            core: MetaTransactionData {
+                to: Address::repeat_byte(0xde).into(),
+                value: 0.into(),
+                data: None,
+                operation: Some(crate::rpc::common::Operations::DelegateCall),
+            },
+            gas: Default::default(),
+            nonce: 3,
+        };
+        let mut bundle = ProposalBundle::new(safe, 5);
+        bundle.insert(tx.into_request(&proposer, safe, 5).await.unwrap());
+
+        assert!(matches!(
+            bundle.cosign(&cosigner).await,
+            Err(SigningClientError::ClientError(
+                ClientError::UnsafeTransaction(_)
+            ))
+        ));
+        assert_eq!(bundle.proposals[0].signatures.len(), 1);
+
+        let allowed = AllowedHazards {
+            delegatecall: true,
+            ..Default::default()
+        };
+        let policy = PolicyEngine::new(
+            crate::policy::Policy::from_json(r#"{"allowedTargets": []}"#).unwrap(),
+        )
+        .unwrap();
+        assert!(matches!(
+            bundle.cosign_with(&cosigner, &allowed, Some(&policy)).await,
+            Err(SigningClientError::ClientError(
+                ClientError::PolicyViolation(_)
+            ))
+        ));
+        bundle.cosign_with(&cosigner, &allowed, None).await.unwrap();
+        assert_eq!(bundle.proposals[0].signatures.len(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_pushes_concurrently() {
+        let safe: Address = SAFE.parse().unwrap();
+        let proposer: LocalWallet =
+            "1c3a7cdd2270579847aaec11680312cbf4d3c36886232b413ab6529593228ec2"
+                .parse()
+                .unwrap();
+        let path = std::env::temp_dir().join(format!("safe-sdk-store-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut requests = vec![];
+        for nonce in 0..8 {
+            let tx = SafeTransactionData::rejection(safe, nonce);
+            requests.push(tx.into_request(&proposer, safe, 5).await.unwrap());
+        }
+        let pushes = requests.into_iter().map(|req| {
+            let store = ProposalStore::new(&path);
+            tokio::spawn(async move { store.push(safe, 5, req).await })
+        });
+        for push in pushes.collect::<Vec<_>>() {
+            push.await.unwrap().unwrap();
+        }
+
+        let store = ProposalStore::new(&path);
+        assert_eq!(store.load().await.unwrap().unwrap().proposals.len(), 8);
+
+        // errors report the stored bundle as found
+        let other = Address::repeat_byte(1);
+        let req = SafeTransactionData::rejection(other, 0)
+            .into_request(&proposer, other, 1)
+            .await
+            .unwrap();
+        let err = store.push(other, 1, req).await.unwrap_err();
+        assert!(matches!(
+            err,
+            BundleError::WrongSafe { expected, expected_chain_id: 1, found, found_chain_id: 5 }
+                if expected == other && found == safe
+        ));
+        let err = ProposalBundle::new(safe, 5)
+            .merge(ProposalBundle::new(other, 1))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            BundleError::WrongSafe { expected, found, .. } if expected == safe && found == other
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,600 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use chrono::{DateTime, Duration, Utc};
+use ethers::{
+    abi::Token,
+    types::{Address, Bytes, U256},
+    utils::id,
+};
+
+use crate::{
+    contracts::{
+        is_multisend, unpack_multisend, ERC20_CONTRACT, MULTISEND_CONTRACT, MULTISEND_SELECTOR,
+    },
+    rpc::{
+        common::Operations,
+        propose::{MetaTransactionData, SafeTransactionData},
+    },
+};
+
+/// Policy configuration errors
+#[derive(Debug, thiserror::Error)]
+pub enum PolicyConfigError {
+    /// Filesystem error
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    /// Invalid policy JSON
+    #[error("{0}")]
+    SerdeError(#[from] serde_json::Error),
+    /// A selector is neither 4 hex bytes nor a function signature
+    #[error("Invalid selector {0:?}")]
+    InvalidSelector(String),
+}
+
+/// A limit on the amount of a token the Safe may send in any 24 hour window
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyLimit {
+    /// The token. `None` for the native asset
+    #[serde(default)]
+    pub token: Option<Address>,
+    /// Maximum amount, in base units
+    #[serde(with = "crate::rpc::common::dec_u256_ser")]
+    pub amount: U256,
+}
+
+/// Rules evaluated before a transaction is signed. Rules apply to each call
+/// in a MultiSend batch. Every rule is disabled by default
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Policy {
+    /// If set, only these targets may be called
+    pub allowed_targets: Option<HashSet<Address>>,
+    /// If set, only these functions may be called, as 4-byte hex selectors
+    /// (`"0xa9059cbb"`) or signatures (`"transfer(address,uint256)"`). Calls
+    /// with no data are always allowed
+    pub allowed_selectors: Option<Vec<String>>,
+    /// Forbid delegatecalls, except to canonical MultiSend deployments and
+    /// `allowed_delegatecall_targets`
+    pub forbid_delegatecall: bool,
+    /// Additional delegatecall targets allowed when `forbid_delegatecall` is
+    /// set
+    pub allowed_delegatecall_targets: HashSet<Address>,
+    /// Limits on native & ERC20 amounts sent in any 24 hour window. ERC20
+    /// approvals count as sent, as the spender may transfer them at will
+    pub daily_limits: Vec<DailyLimit>,
+    /// Require that `refundReceiver` is zero, so that gas refunds, if any,
+    /// go to the executor
+    pub require_zero_refund_receiver: bool,
+}
+
+impl Policy {
+    /// Load a policy from JSON
+    pub fn from_json(json: &str) -> Result<Self, PolicyConfigError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Load a policy from a JSON file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, PolicyConfigError> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// Resolve `allowed_selectors` to 4-byte selectors
+    pub fn selectors(&self) -> Result<Option<HashSet<[u8; 4]>>, PolicyConfigError> {
+        let selectors = match &self.allowed_selectors {
+            Some(selectors) => selectors,
+            None => return Ok(None),
+        };
+        selectors
+            .iter()
+            .map(|s| {
+                if s.contains('(') {
+                    return Ok(id(s));
+                }
+                hex::decode(s.trim_start_matches("0x"))
+                    .ok()
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .ok_or_else(|| PolicyConfigError::InvalidSelector(s.clone()))
+            })
+            .collect::<Result<_, _>>()
+            .map(Some)
+    }
+}
+
+/// A policy rule broken by a transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// The target is not in `allowed_targets`
+    TargetNotAllowed {
+        /// The target
+        to: Address,
+    },
+    /// The function is not in `allowed_selectors`
+    SelectorNotAllowed {
+        /// The target
+        to: Address,
+        /// The function selector
+        selector: [u8; 4],
+    },
+    /// Delegatecall to a target other than a known MultiSend
+    DelegateCall {
+        /// The target
+        to: Address,
+    },
+    /// The transfer would exceed a daily limit
+    DailyLimitExceeded {
+        /// The token. `None` for the native asset
+        token: Option<Address>,
+        /// The limit
+        limit: U256,
+        /// Amount sent in the last 24 hours
+        spent: U256,
+        /// Amount of this transaction
+        amount: U256,
+    },
+    /// The refund receiver is set, rather than zero
+    RefundReceiverNotZero {
+        /// The refund receiver
+        refund_receiver: Address,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::TargetNotAllowed { to } => write!(f, "target {to:?} is not allowed"),
+            Violation::SelectorNotAllowed { to, selector } => write!(
+                f,
+                "function 0x{} on {to:?} is not allowed",
+                hex::encode(selector)
+            ),
+            Violation::DelegateCall { to } => write!(f, "delegatecall to {to:?} is not allowed"),
+            Violation::DailyLimitExceeded {
+                token,
+                limit,
+                spent,
+                amount,
+            } => write!(
+                f,
+                "sending {amount} of {} exceeds daily limit {limit} ({spent} already sent)",
+                token.map_or("native asset".to_owned(), |t| format!("{t:?}"))
+            ),
+            Violation::RefundReceiverNotZero { refund_receiver } => {
+                write!(f, "refund receiver {refund_receiver:?} is not zero")
+            }
+        }
+    }
+}
+
+/// A transaction was rejected by policy
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Transaction violates policy: {}", .violations.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+pub struct PolicyViolations {
+    /// Every rule broken by the transaction
+    pub violations: Vec<Violation>,
+}
+
+#[derive(Debug, Clone)]
+struct Spend {
+    id: u64,
+    at: DateTime<Utc>,
+    safe_address: Address,
+    token: Option<Address>,
+    amount: U256,
+}
+
+/// Evaluates a [`Policy`] against transactions, tracking amounts sent for
+/// daily limits. Amounts are tracked in memory, from transactions approved by
+/// this engine
+#[derive(Debug)]
+pub struct PolicyEngine {
+    policy: Policy,
+    selectors: Option<HashSet<[u8; 4]>>,
+    ledger: Mutex<Vec<Spend>>,
+    next_spend: AtomicU64,
+}
+
+/// Transfers recorded toward daily limits by
+/// [`PolicyEngine::check_and_record`]. Pass to [`PolicyEngine::rollback`] if
+/// the transaction is not signed or submitted after all
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpendRecord(u64);
+
+impl PolicyEngine {
+    /// Instantiate from a policy. Errors if its selectors are invalid
+    pub fn new(policy: Policy) -> Result<Self, PolicyConfigError> {
+        Ok(Self {
+            selectors: policy.selectors()?,
+            policy,
+            ledger: Default::default(),
+            next_spend: Default::default(),
+        })
+    }
+
+    /// Load the policy from a JSON file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, PolicyConfigError> {
+        Self::new(Policy::from_file(path)?)
+    }
+
+    /// The policy
+    pub fn policy(&self) -> &Policy {
+        &self.policy
+    }
+
+    /// Evaluate the policy against a transaction of the Safe at
+    /// `safe_address`, without recording its transfers. The canonical
+    /// rejection transaction always passes
+    pub fn check(
+        &self,
+        safe_address: Address,
+        tx: &SafeTransactionData,
+    ) -> Result<(), PolicyViolations> {
+        let mut ledger = self.ledger.lock().expect("poisoned");
+        self.evaluate(&mut ledger, safe_address, tx).map(|_| ())
+    }
+
+    /// Evaluate the policy against a transaction, and record its transfers
+    /// toward daily limits if it passes. The ledger stays locked between
+    /// evaluating & recording, so concurrent signers cannot both pass the
+    /// same limit
+    pub fn check_and_record(
+        &self,
+        safe_address: Address,
+        tx: &SafeTransactionData,
+    ) -> Result<SpendRecord, PolicyViolations> {
+        let mut ledger = self.ledger.lock().expect("poisoned");
+        let outflows = self.evaluate(&mut ledger, safe_address, tx)?;
+        let id = self.next_spend.fetch_add(1, Ordering::Relaxed);
+        let at = Utc::now();
+        ledger.extend(outflows.into_iter().map(|(token, amount)| Spend {
+            id,
+            at,
+            safe_address,
+            token,
+            amount,
+        }));
+        Ok(SpendRecord(id))
+    }
+
+    /// Remove transfers recorded by [`PolicyEngine::check_and_record`], e.g.
+    /// if signing or submitting the transaction failed
+    pub fn rollback(&self, record: SpendRecord) {
+        self.ledger
+            .lock()
+            .expect("poisoned")
+            .retain(|s| s.id != record.0);
+    }
+
+    /// Amount of `token` sent by the Safe in the last 24 hours
+    pub fn spent(&self, safe_address: Address, token: Option<Address>) -> U256 {
+        let mut ledger = self.ledger.lock().expect("poisoned");
+        Self::spent_in(&mut ledger, safe_address, token)
+    }
+
+    fn spent_in(ledger: &mut Vec<Spend>, safe_address: Address, token: Option<Address>) -> U256 {
+        let since = Utc::now() - Duration::days(1);
+        ledger.retain(|s| s.at > since);
+        ledger
+            .iter()
+            .filter(|s| s.safe_address == safe_address && s.token == token)
+            .fold(U256::zero(), |acc, s| acc.saturating_add(s.amount))
+    }
+
+    /// Evaluate against the locked ledger, returning the transaction's
+    /// outflows by token
+    fn evaluate(
+        &self,
+        ledger: &mut Vec<Spend>,
+        safe_address: Address,
+        tx: &SafeTransactionData,
+    ) -> Result<HashMap<Option<Address>, U256>, PolicyViolations> {
+        let mut violations = vec![];
+        let mut outflows = HashMap::new();
+
+        if *tx == SafeTransactionData::rejection(safe_address, tx.nonce) {
+            return Ok(outflows);
+        }
+
+        if self.policy.require_zero_refund_receiver {
+            let refund_receiver = Address::from(tx.gas.refund_receiver);
+            if !refund_receiver.is_zero() {
+                violations.push(Violation::RefundReceiverNotZero { refund_receiver });
+            }
+        }
+
+        self.evaluate_call(safe_address, &tx.core, &mut violations, &mut outflows);
+
+        for limit in self.policy.daily_limits.iter() {
+            let amount = outflows.get(&limit.token).copied().unwrap_or_default();
+            if amount.is_zero() {
+                continue;
+            }
+            let spent = Self::spent_in(ledger, safe_address, limit.token);
+            if spent.saturating_add(amount) > limit.amount {
+                violations.push(Violation::DailyLimitExceeded {
+                    token: limit.token,
+                    limit: limit.amount,
+                    spent,
+                    amount,
+                });
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(outflows)
+        } else {
+            Err(PolicyViolations { violations })
+        }
+    }
+
+    fn evaluate_call(
+        &self,
+        safe_address: Address,
+        call: &MetaTransactionData,
+        violations: &mut Vec<Violation>,
+        outflows: &mut HashMap<Option<Address>, U256>,
+    ) {
+        let to = Address::from(call.to);
+        let data = call.data.as_deref().unwrap_or_default();
+
+        if call.operation == Some(Operations::DelegateCall) {
+            if is_multisend(to) && data.starts_with(&MULTISEND_SELECTOR) {
+                let nested = MULTISEND_CONTRACT
+                    .decode::<Bytes, _>("multiSend", data)
+                    .ok()
+                    .and_then(|packed| unpack_multisend(&packed));
+                match nested {
+                    Some(nested) => {
+                        for call in nested.iter() {
+                            self.evaluate_call(safe_address, call, violations, outflows);
+                        }
+                    }
+                    // undecodable batches cannot be checked
+                    None => violations.push(Violation::DelegateCall { to }),
+                }
+                return;
+            }
+            if self.policy.forbid_delegatecall
+                && !self.policy.allowed_delegatecall_targets.contains(&to)
+            {
+                violations.push(Violation::DelegateCall { to });
+            }
+        }
+
+        if let Some(allowed) = &self.policy.allowed_targets {
+            if !allowed.contains(&to) {
+                violations.push(Violation::TargetNotAllowed { to });
+            }
+        }
+
+        if let (Some(allowed), true) = (&self.selectors, data.len() >= 4) {
+            let selector: [u8; 4] = data[..4].try_into().expect("checked length");
+            if !allowed.contains(&selector) {
+                violations.push(Violation::SelectorNotAllowed { to, selector });
+            }
+        }
+
+        // delegatecalls do not transfer value
+        if call.operation != Some(Operations::DelegateCall) && !call.value.is_zero() {
+            let spent = outflows.entry(None).or_insert_with(U256::zero);
+            *spent = spent.saturating_add(call.value);
+        }
+        if let Some(amount) = erc20_outflow(safe_address, data) {
+            let spent = outflows.entry(Some(to)).or_insert_with(U256::zero);
+            *spent = spent.saturating_add(amount);
+        }
+    }
+}
+
+/// Amount sent from the Safe by an ERC20 `transfer` or `transferFrom` the
+/// Safe, or made spendable by `approve` or `increaseAllowance`
+fn erc20_outflow(safe_address: Address, data: &[u8]) -> Option<U256> {
+    if data.len() < 4 {
+        return None;
+    }
+    let abi = ERC20_CONTRACT.abi();
+    let function = abi.functions().find(|f| f.short_signature() == data[..4])?;
+    let tokens = function.decode_input(&data[4..]).ok()?;
+    match (function.name.as_str(), tokens.as_slice()) {
+        ("transfer" | "approve" | "increaseAllowance", [_, Token::Uint(amount)]) => Some(*amount),
+        ("transferFrom", [Token::Address(from), _, Token::Uint(amount)])
+            if *from == safe_address =>
+        {
+            Some(*amount)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        contracts::{multisend_calldata, MULTISEND_ADDRESSES},
+        tx::{erc20_transfer, native_transfer},
+    };
+
+    const SAFE: &str = "0x38CD8Fa77ECEB4b1edB856Ed27aac6A6c6Dc88ca";
+    const USDC: &str = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48";
+    const PAYEE: &str = "0x425249Cf0F2f91f488E24cF7B1AA3186748f7516";
+
+    fn safe_tx(core: MetaTransactionData) -> SafeTransactionData {
+        SafeTransactionData {
+            core,
+            gas: Default::default(),
+            nonce: 1,
+        }
+    }
+
+    fn engine() -> PolicyEngine {
+        let policy = Policy::from_json(&format!(
+            r#"{{
+                "allowedTargets": ["{USDC}", "{PAYEE}"],
+                "allowedSelectors": ["transfer(address,uint256)"],
+                "forbidDelegatecall": true,
+                "dailyLimits": [
+                    {{"token": "{USDC}", "amount": "1000000000"}},
+                    {{"amount": "1000000000000000000"}}
+                ],
+                "requireZeroRefundReceiver": true
+            }}"#
+        ))
+        .unwrap();
+        PolicyEngine::new(policy).unwrap()
+    }
+
+    #[test]
+    fn it_enforces_daily_limits_across_batches() {
+        let engine = engine();
+        let safe: Address = SAFE.parse().unwrap();
+        let usdc: Address = USDC.parse().unwrap();
+        let payee: Address = PAYEE.parse().unwrap();
+
+        let transfer = safe_tx(erc20_transfer(usdc, payee, 600_000_000u64.into()));
+        engine.check_and_record(safe, &transfer).unwrap();
+        assert_eq!(engine.spent(safe, Some(usdc)), 600_000_000u64.into());
+
+        // second transfer, batched with a native send, exceeds the limit
+        let batch = safe_tx(MetaTransactionData {
+            to: MULTISEND_ADDRESSES[1].into(),
+            value: U256::zero(),
+            data: Some(multisend_calldata(&[
+                erc20_transfer(usdc, payee, 600_000_000u64.into()),
+                native_transfer(payee, U256::exp10(17)),
+            ])),
+            operation: Some(Operations::DelegateCall),
+        });
+        let err = engine.check_and_record(safe, &batch).unwrap_err();
+        assert_eq!(
+            err.violations,
+            vec![Violation::DailyLimitExceeded {
+                token: Some(usdc),
+                limit: 1_000_000_000u64.into(),
+                spent: 600_000_000u64.into(),
+                amount: 600_000_000u64.into(),
+            }]
+        );
+        // rejected txns are not recorded
+        assert_eq!(engine.spent(safe, None), U256::zero());
+
+        // rejections always pass
+        engine
+            .check(safe, &SafeTransactionData::rejection(safe, 1))
+            .unwrap();
+    }
+
+    #[test]
+    fn it_passes_one_of_two_concurrent_overlimit_txns() {
+        let engine = engine();
+        let safe: Address = SAFE.parse().unwrap();
+        let usdc: Address = USDC.parse().unwrap();
+        let payee: Address = PAYEE.parse().unwrap();
+        let transfer = safe_tx(erc20_transfer(usdc, payee, 600_000_000u64.into()));
+
+        let barrier = std::sync::Barrier::new(2);
+        let passed = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..2)
+                .map(|_| {
+                    scope.spawn(|| {
+                        barrier.wait();
+                        engine.check_and_record(safe, &transfer).is_ok()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap())
+                .filter(|passed| *passed)
+                .count()
+        });
+        assert_eq!(passed, 1);
+        assert_eq!(engine.spent(safe, Some(usdc)), 600_000_000u64.into());
+
+        // rolled back spends no longer count toward the limit
+        let engine = self::engine();
+        let record = engine.check_and_record(safe, &transfer).unwrap();
+        engine.rollback(record);
+        assert_eq!(engine.spent(safe, Some(usdc)), U256::zero());
+        engine.check_and_record(safe, &transfer).unwrap();
+    }
+
+    #[test]
+    fn it_reports_every_violation() {
+        let engine = engine();
+        let safe: Address = SAFE.parse().unwrap();
+        let stranger = Address::repeat_byte(0x99);
+
+        let mut tx = safe_tx(MetaTransactionData {
+            to: stranger.into(),
+            value: U256::zero(),
+            data: Some(vec![0x12, 0x34, 0x56, 0x78].into()),
+            operation: Some(Operations::DelegateCall),
+        });
+        tx.gas.refund_receiver = stranger.into();
+
+        let err = engine.check(safe, &tx).unwrap_err();
+        assert_eq!(
+            err.violations,
+            vec![
+                Violation::RefundReceiverNotZero {
+                    refund_receiver: stranger
+                },
+                Violation::DelegateCall { to: stranger },
+                Violation::TargetNotAllowed { to: stranger },
+                Violation::SelectorNotAllowed {
+                    to: stranger,
+                    selector: [0x12, 0x34, 0x56, 0x78]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn it_counts_approvals_toward_daily_limits() {
+        let usdc: Address = USDC.parse().unwrap();
+        let safe: Address = SAFE.parse().unwrap();
+        let spender = Address::repeat_byte(0x99);
+        let engine = PolicyEngine::new(
+            Policy::from_json(&format!(
+                r#"{{"dailyLimits": [{{"token": "{USDC}", "amount": "1000000000"}}]}}"#
+            ))
+            .unwrap(),
+        )
+        .unwrap();
+        let call = |function: &str, amount: U256| {
+            safe_tx(MetaTransactionData {
+                to: usdc.into(),
+                value: U256::zero(),
+                data: Some(ERC20_CONTRACT.encode(function, (spender, amount)).unwrap()),
+                operation: None,
+            })
+        };
+
+        let err = engine.check(safe, &call("approve", U256::MAX)).unwrap_err();
+        assert_eq!(
+            err.violations,
+            vec![Violation::DailyLimitExceeded {
+                token: Some(usdc),
+                limit: 1_000_000_000u64.into(),
+                spent: U256::zero(),
+                amount: U256::MAX,
+            }]
+        );
+
+        engine
+            .check_and_record(safe, &call("increaseAllowance", 600_000_000u64.into()))
+            .unwrap();
+        assert_eq!(engine.spent(safe, Some(usdc)), 600_000_000u64.into());
+        assert!(engine
+            .check(safe, &call("approve", 600_000_000u64.into()))
+            .is_err());
+    }
+}
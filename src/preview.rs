@@ -0,0 +1,657 @@
+use std::{collections::HashMap, fmt};
+
+use ethers::types::{transaction::eip712::Eip712, Address, Bytes, H256, U256};
+use serde::Serialize;
+
+use crate::{
+    client::ClientResult,
+    decode::Decoder,
+    rpc::{
+        balances::BalanceResponse,
+        common::{ChecksumAddress, Operations},
+        msig_history::{DecodedData, MsigTxResponse, ParameterValue},
+        propose::{ProposalOrigin, SafeTransactionData},
+    },
+    safety::{detect_hazards, Hazard},
+    tx::{format_amount, NATIVE_DECIMALS},
+    SafeClient,
+};
+
+/// Symbol & decimals of a token, used to format amounts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenMeta {
+    /// The token symbol
+    pub symbol: String,
+    /// The token decimals
+    pub decimals: u32,
+}
+
+/// Renders transactions as previews for owners to review before signing.
+/// Calldata is decoded locally, never trusting the service's decoding
+#[derive(Debug, Clone)]
+pub struct Previewer {
+    decoder: Decoder,
+    labels: HashMap<Address, String>,
+    tokens: HashMap<Address, TokenMeta>,
+    native: TokenMeta,
+    signer: Option<Address>,
+}
+
+impl Default for Previewer {
+    fn default() -> Self {
+        Self {
+            decoder: Default::default(),
+            labels: Default::default(),
+            tokens: Default::default(),
+            native: TokenMeta {
+                symbol: "ETH".to_owned(),
+                decimals: NATIVE_DECIMALS,
+            },
+            signer: None,
+        }
+    }
+}
+
+impl Previewer {
+    /// Use a decoder with additional ABIs
+    pub fn with_decoder(mut self, decoder: Decoder) -> Self {
+        self.decoder = decoder;
+        self
+    }
+
+    /// Label an address, e.g. `"treasury"`
+    pub fn with_label(mut self, address: Address, label: impl Into<String>) -> Self {
+        self.labels.insert(address, label.into());
+        self
+    }
+
+    /// Format amounts of `token` with its symbol & decimals. Tokens are also
+    /// labelled with their symbol, unless already labelled
+    pub fn with_token(mut self, token: Address, symbol: impl Into<String>, decimals: u32) -> Self {
+        let symbol = symbol.into();
+        self.labels.entry(token).or_insert_with(|| symbol.clone());
+        self.tokens.insert(token, TokenMeta { symbol, decimals });
+        self
+    }
+
+    /// Add the tokens of a Safe's balances. See [`Previewer::with_token`]
+    pub fn with_balances(self, balances: &[BalanceResponse]) -> Self {
+        balances.iter().fold(self, |previewer, balance| {
+            match (balance.token_address, &balance.token) {
+                (Some(address), Some(info)) => match info.decimals {
+                    Some(decimals) => previewer.with_token(address, &info.symbol, decimals),
+                    None => previewer,
+                },
+                _ => previewer,
+            }
+        })
+    }
+
+    /// Symbol & decimals of the native asset. Defaults to ETH
+    pub fn with_native(mut self, symbol: impl Into<String>, decimals: u32) -> Self {
+        self.native = TokenMeta {
+            symbol: symbol.into(),
+            decimals,
+        };
+        self
+    }
+
+    /// The address about to sign. Refunds to the signer are not reported as
+    /// hazards. See [`crate::safety::detect_hazards`]
+    pub fn with_signer(mut self, signer: Address) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Preview a transaction of the Safe at `safe_address`
+    pub fn preview(
+        &self,
+        safe_address: Address,
+        chain_id: u64,
+        tx: &SafeTransactionData,
+    ) -> TxPreview {
+        let operation = tx.core.operation.unwrap_or(Operations::Call);
+        let data = tx.core.data.as_ref().filter(|d| !d.is_empty());
+        let decoded = data.and_then(|d| self.decoder.decode_data(d));
+        TxPreview {
+            safe: self.target(safe_address),
+            chain_id,
+            nonce: tx.nonce,
+            safe_tx_hash: tx.safe_tx_hash(safe_address, chain_id),
+            domain_hash: tx
+                .eip712(safe_address, chain_id)
+                .domain_separator()
+                .expect("infallible")
+                .into(),
+            call: self.call(
+                operation,
+                tx.core.to.into(),
+                tx.core.value,
+                data,
+                decoded.as_ref(),
+            ),
+            refund: RefundPreview {
+                safe_tx_gas: tx.gas.safe_tx_gas,
+                base_gas: tx.gas.base_gas,
+                gas_price: tx.gas.gas_price,
+                gas_token: self.target(tx.gas.gas_token.into()),
+                refund_receiver: self.target(tx.gas.refund_receiver.into()),
+            },
+            hazards: detect_hazards(safe_address, self.signer.unwrap_or_default(), tx),
+            service: None,
+        }
+    }
+
+    /// Preview a multisig transaction from the API. The safe tx hash is
+    /// recomputed, and a differing service hash is reported
+    pub fn preview_msig(&self, chain_id: u64, tx: &MsigTxResponse) -> TxPreview {
+        let mut preview = self.preview(tx.safe, chain_id, &SafeTransactionData::from(tx));
+        preview.service = Some(ServicePreview {
+            safe_tx_hash: tx.safe_tx_hash,
+            proposer: tx.proposer.map(|p| self.target(p)),
+            origin: tx.proposal_origin(),
+            confirmations: tx
+                .confirmations
+                .iter()
+                .map(|c| self.target(c.owner))
+                .collect(),
+            confirmations_required: tx.confirmations_required,
+            is_executed: tx.is_executed,
+        });
+        preview
+    }
+
+    fn target(&self, address: Address) -> Target {
+        Target {
+            address: address.into(),
+            label: self.labels.get(&address).cloned(),
+        }
+    }
+
+    fn amount(&self, token: Option<Address>, raw: U256) -> Amount {
+        let meta = match token {
+            Some(token) => self.tokens.get(&token),
+            None => Some(&self.native),
+        };
+        Amount {
+            raw,
+            formatted: meta.map(|m| format_amount(raw, m.decimals)),
+            symbol: meta.map(|m| m.symbol.clone()),
+        }
+    }
+
+    fn call(
+        &self,
+        operation: Operations,
+        to: Address,
+        value: U256,
+        data: Option<&Bytes>,
+        decoded: Option<&DecodedData>,
+    ) -> CallPreview {
+        CallPreview {
+            operation,
+            to: self.target(to),
+            value: self.amount(None, value),
+            data: data.cloned(),
+            method: decoded.map(|d| d.method.clone()),
+            arguments: decoded
+                .iter()
+                .flat_map(|d| d.parameters.iter())
+                .map(|p| ArgumentPreview {
+                    name: p.name.clone(),
+                    param_type: p.param_type.clone(),
+                    value: p.value.to_string(),
+                    label: p
+                        .value
+                        .as_address()
+                        .and_then(|a| self.labels.get(&a).cloned()),
+                })
+                .collect(),
+            token_transfer: decoded.and_then(|d| self.token_transfer(to, d)),
+            calls: decoded
+                .iter()
+                .flat_map(|d| d.nested_transactions())
+                .map(|tx| {
+                    self.call(
+                        tx.operation,
+                        tx.to,
+                        tx.value,
+                        tx.data.as_ref(),
+                        tx.data_decoded.as_ref(),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    fn token_transfer(&self, token: Address, decoded: &DecodedData) -> Option<TokenTransfer> {
+        let values: Vec<_> = decoded.parameters.iter().map(|p| &p.value).collect();
+        let (kind, from, to, amount) = match (decoded.method.as_str(), values.as_slice()) {
+            ("transfer", [to, amount]) => (TransferKind::Transfer, None, to, amount),
+            ("approve", [to, amount]) => (TransferKind::Approve, None, to, amount),
+            ("transferFrom", [from, to, amount]) => {
+                (TransferKind::TransferFrom, Some(from), to, amount)
+            }
+            _ => return None,
+        };
+        let address = |v: &ParameterValue| v.as_address().map(|a| self.target(a));
+        Some(TokenTransfer {
+            kind,
+            token: self.target(token),
+            from: match from {
+                Some(from) => Some(address(from)?),
+                None => None,
+            },
+            to: address(to)?,
+            amount: self.amount(Some(token), amount.as_uint()?),
+        })
+    }
+}
+
+impl SafeClient {
+    /// Create a previewer for the Safe at `safe_address`, formatting amounts
+    /// of the tokens it holds, and labelling the Safe itself
+    pub async fn previewer(&self, safe_address: Address) -> ClientResult<Previewer> {
+        Ok(Previewer::default()
+            .with_balances(&self.balances(safe_address).await?)
+            .with_label(safe_address, "this Safe"))
+    }
+}
+
+/// An address, with its label if known
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Target {
+    /// The address
+    pub address: ChecksumAddress,
+    /// The label, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.label {
+            Some(label) => write!(f, "{} ({label})", self.address),
+            None => write!(f, "{}", self.address),
+        }
+    }
+}
+
+/// An amount in base units, formatted if the token's decimals are known
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Amount {
+    /// The amount in base units
+    #[serde(with = "crate::rpc::common::dec_u256_ser")]
+    pub raw: U256,
+    /// The amount with decimals, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub formatted: Option<String>,
+    /// The token symbol, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.formatted, &self.symbol) {
+            (Some(formatted), Some(symbol)) => write!(f, "{formatted} {symbol}"),
+            _ => write!(f, "{} (base units)", self.raw),
+        }
+    }
+}
+
+/// Kind of token movement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TransferKind {
+    /// `transfer` from the Safe
+    Transfer,
+    /// `transferFrom`
+    TransferFrom,
+    /// `approve` of a spender
+    Approve,
+}
+
+/// A token transfer or approval, recognized from decoded calldata
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenTransfer {
+    /// Transfer or approval
+    pub kind: TransferKind,
+    /// The token
+    pub token: Target,
+    /// The owner, for `transferFrom`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<Target>,
+    /// The recipient or spender
+    pub to: Target,
+    /// The amount. For ERC721 `transferFrom`, the token ID
+    pub amount: Amount,
+}
+
+/// A decoded call argument
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArgumentPreview {
+    /// Parameter name
+    pub name: String,
+    /// Solidity type of parameter
+    #[serde(rename = "type")]
+    pub param_type: String,
+    /// Parameter value
+    pub value: String,
+    /// Label of an address value, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// Preview of a call, or of a call nested in a MultiSend batch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallPreview {
+    /// CALL or DELEGATECALL
+    #[serde(serialize_with = "ser_operation")]
+    pub operation: Operations,
+    /// The target
+    pub to: Target,
+    /// Native asset value
+    pub value: Amount,
+    /// Raw calldata, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Bytes>,
+    /// Decoded method, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    /// Decoded arguments
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub arguments: Vec<ArgumentPreview>,
+    /// Token transfer or approval, if recognized
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_transfer: Option<TokenTransfer>,
+    /// Calls nested in a MultiSend batch
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub calls: Vec<CallPreview>,
+}
+
+/// Gas refund settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefundPreview {
+    /// Gas forwarded to the call. 0 for all available
+    #[serde(with = "crate::rpc::common::dec_u256_ser")]
+    pub safe_tx_gas: U256,
+    /// Gas independent of the call
+    #[serde(with = "crate::rpc::common::dec_u256_ser")]
+    pub base_gas: U256,
+    /// Refund gas price. 0 for no refund
+    #[serde(with = "crate::rpc::common::dec_u256_ser")]
+    pub gas_price: U256,
+    /// Refund token, zero for the native asset
+    pub gas_token: Target,
+    /// Refund receiver, zero for the executor
+    pub refund_receiver: Target,
+}
+
+/// Service state of a proposed transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServicePreview {
+    /// The safe tx hash reported by the service
+    pub safe_tx_hash: H256,
+    /// The proposer, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proposer: Option<Target>,
+    /// Origin metadata set by the proposer, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<ProposalOrigin>,
+    /// Owners who confirmed
+    pub confirmations: Vec<Target>,
+    /// Confirmations required, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confirmations_required: Option<u32>,
+    /// True if executed
+    pub is_executed: bool,
+}
+
+/// Structured preview of a Safe transaction. Renders as plain text via
+/// `Display`, or as JSON via [`TxPreview::to_json`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxPreview {
+    /// The Safe
+    pub safe: Target,
+    /// The chain ID
+    pub chain_id: u64,
+    /// The Safe nonce
+    pub nonce: u64,
+    /// The safe tx hash, computed locally
+    pub safe_tx_hash: H256,
+    /// The EIP712 domain separator of the Safe
+    pub domain_hash: H256,
+    /// The call
+    pub call: CallPreview,
+    /// Gas refund settings
+    pub refund: RefundPreview,
+    /// Hazards detected. See [`crate::safety`]
+    #[serde(serialize_with = "ser_hazards")]
+    pub hazards: Vec<Hazard>,
+    /// Service state, for transactions from the API
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service: Option<ServicePreview>,
+}
+
+impl TxPreview {
+    /// True if the service reports a safe tx hash differing from the locally
+    /// computed one
+    pub fn hash_mismatch(&self) -> bool {
+        self.service
+            .as_ref()
+            .is_some_and(|s| s.safe_tx_hash != self.safe_tx_hash)
+    }
+
+    /// Render as pretty JSON
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+fn ser_operation<S: serde::Serializer>(op: &Operations, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(operation_name(*op))
+}
+
+fn ser_hazards<S: serde::Serializer>(hazards: &[Hazard], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(hazards.iter().map(ToString::to_string))
+}
+
+fn operation_name(op: Operations) -> &'static str {
+    match op {
+        Operations::Call => "CALL",
+        Operations::DelegateCall => "DELEGATECALL",
+    }
+}
+
+fn write_call(f: &mut fmt::Formatter<'_>, call: &CallPreview, depth: usize) -> fmt::Result {
+    let indent = "  ".repeat(depth);
+    writeln!(f, "{indent}{} {}", operation_name(call.operation), call.to)?;
+    if !call.value.raw.is_zero() {
+        writeln!(f, "{indent}  value: {}", call.value)?;
+    }
+    match &call.method {
+        Some(method) => {
+            writeln!(f, "{indent}  method: {method}")?;
+            for arg in call.arguments.iter().filter(|_| call.calls.is_empty()) {
+                match &arg.label {
+                    Some(label) => {
+                        writeln!(f, "{indent}    {}: {} ({label})", arg.name, arg.value)?
+                    }
+                    None => writeln!(f, "{indent}    {}: {}", arg.name, arg.value)?,
+                }
+            }
+        }
+        None => {
+            if let Some(data) = &call.data {
+                writeln!(f, "{indent}  data: {data}")?;
+            }
+        }
+    }
+    if let Some(transfer) = &call.token_transfer {
+        let kind = match transfer.kind {
+            TransferKind::Transfer => "transfer",
+            TransferKind::TransferFrom => "transfer",
+            TransferKind::Approve => "approve",
+        };
+        write!(f, "{indent}  => {kind} {}", transfer.amount)?;
+        if let Some(from) = &transfer.from {
+            write!(f, " from {from}")?;
+        }
+        let to = match transfer.kind {
+            TransferKind::Approve => "spender",
+            _ => "to",
+        };
+        writeln!(f, " {to} {}", transfer.to)?;
+    }
+    if !call.calls.is_empty() {
+        writeln!(f, "{indent}  batch of {} calls:", call.calls.len())?;
+        for (i, nested) in call.calls.iter().enumerate() {
+            writeln!(f, "{indent}  [{i}]")?;
+            write_call(f, nested, depth + 2)?;
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for TxPreview {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Safe:         {}", self.safe)?;
+        writeln!(f, "Chain ID:     {}", self.chain_id)?;
+        writeln!(f, "Nonce:        {}", self.nonce)?;
+        writeln!(f, "Safe tx hash: {:?}", self.safe_tx_hash)?;
+        writeln!(f, "Domain hash:  {:?}", self.domain_hash)?;
+        if let Some(service) = &self.service {
+            if self.hash_mismatch() {
+                writeln!(
+                    f,
+                    "WARNING: service reports safe tx hash {:?}",
+                    service.safe_tx_hash
+                )?;
+            }
+            if let Some(proposer) = &service.proposer {
+                writeln!(f, "Proposer:     {proposer}")?;
+            }
+            if let Some(origin) = &service.origin {
+                writeln!(f, "Origin:       {origin}")?;
+            }
+            match service.confirmations_required {
+                Some(required) => writeln!(
+                    f,
+                    "Confirmed:    {}/{required}",
+                    service.confirmations.len()
+                )?,
+                None => writeln!(f, "Confirmed:    {}", service.confirmations.len())?,
+            }
+            for owner in &service.confirmations {
+                writeln!(f, "  {owner}")?;
+            }
+            if service.is_executed {
+                writeln!(f, "Executed")?;
+            }
+        }
+
+        writeln!(f, "Call:")?;
+        write_call(f, &self.call, 1)?;
+
+        let refund = &self.refund;
+        if refund.gas_price.is_zero() {
+            writeln!(f, "Gas refund:   none")?;
+        } else {
+            let token = if refund.gas_token.address.is_zero() {
+                "native asset".to_owned()
+            } else {
+                refund.gas_token.to_string()
+            };
+            let receiver = if refund.refund_receiver.address.is_zero() {
+                "executor".to_owned()
+            } else {
+                refund.refund_receiver.to_string()
+            };
+            writeln!(
+                f,
+                "Gas refund:   price {} in {token} to {receiver}",
+                refund.gas_price
+            )?;
+        }
+        writeln!(
+            f,
+            "Gas:          safeTxGas {}, baseGas {}",
+            refund.safe_tx_gas, refund.base_gas
+        )?;
+
+        if !self.hazards.is_empty() {
+            writeln!(f, "HAZARDS:")?;
+            for hazard in &self.hazards {
+                writeln!(f, "  - {hazard}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        contracts::{multisend_calldata, MULTISEND_ADDRESSES},
+        rpc::propose::MetaTransactionData,
+        tx::{erc20_transfer, native_transfer},
+    };
+
+    #[test]
+    fn it_previews_batches() {
+        let safe = Address::repeat_byte(0x5a);
+        let usdc = Address::repeat_byte(0xc0);
+        let alice = Address::repeat_byte(0xa1);
+        let tx = SafeTransactionData {
+            core: MetaTransactionData {
+                to: MULTISEND_ADDRESSES[0].into(),
+                value: U256::zero(),
+                data: Some(multisend_calldata(&[
+                    erc20_transfer(usdc, alice, 1_500_000.into()),
+                    native_transfer(alice, U256::exp10(18)),
+                ])),
+                operation: Some(Operations::DelegateCall),
+            },
+            gas: Default::default(),
+            nonce: 7,
+        };
+
+        let preview = Previewer::default()
+            .with_token(usdc, "USDC", 6)
+            .with_label(alice, "alice")
+            .preview(safe, 1, &tx);
+
+        assert_eq!(preview.safe_tx_hash, tx.safe_tx_hash(safe, 1));
+        assert!(preview.hazards.is_empty());
+        assert_eq!(preview.call.method.as_deref(), Some("multiSend"));
+        assert_eq!(preview.call.calls.len(), 2);
+
+        let transfer = preview.call.calls[0].token_transfer.as_ref().unwrap();
+        assert_eq!(transfer.amount.to_string(), "1.500000 USDC");
+        assert_eq!(transfer.to.label.as_deref(), Some("alice"));
+        assert_eq!(
+            preview.call.calls[1].value.to_string(),
+            "1.000000000000000000 ETH"
+        );
+
+        let text = preview.to_string();
+        assert!(text.contains("Nonce:        7"));
+        assert!(text.contains("=> transfer 1.500000 USDC to"));
+        assert!(text.contains("Gas refund:   none"));
+
+        let json: serde_json::Value = serde_json::from_str(&preview.to_json().unwrap()).unwrap();
+        assert_eq!(json["call"]["operation"], "DELEGATECALL");
+        assert_eq!(
+            json["call"]["calls"][0]["tokenTransfer"]["amount"]["raw"],
+            "1500000"
+        );
+    }
+}
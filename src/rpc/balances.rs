@@ -84,6 +84,7 @@ pub struct BalanceResponse {
     /// The token info (null for native tokens)
     pub token: Option<Erc20Info>,
     /// The balance of the safe for the token
+    #[serde(with = "crate::rpc::common::dec_u256_ser")]
     pub balance: U256,
     /// The value in eth of the token
     pub eth_value: String,
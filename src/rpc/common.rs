@@ -1,8 +1,14 @@
-use std::{fmt::Display, future::Future, str::FromStr};
+use std::{
+    fmt::Display,
+    future::Future,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use ethers::{
     abi::{ethereum_types::FromDecStrErr, InvalidOutputType, Token, Tokenizable},
     types::{Address, Bytes, H256, U256},
+    utils::keccak256,
 };
 use once_cell::sync::Lazy;
 use reqwest::Url;
@@ -21,6 +27,18 @@ pub static SAFE_TX_TYPEHASH: Lazy<H256> = Lazy::new(|| {
         .unwrap()
 });
 
+/// EIP-712 Safe message typehash. Copied from the gnosis safe
+/// CompatibilityFallbackHandler
+///
+/// keccak256(
+///     "SafeMessage(bytes message)"
+/// );
+pub static SAFE_MESSAGE_TYPEHASH: Lazy<H256> = Lazy::new(|| {
+    "0x60b3cbf8b4a223d68d641b3b6ddf9a298e7f33710cf3d3a9d1146b5a6150fbca"
+        .parse()
+        .unwrap()
+});
+
 /// EIP-712 typehash domain binding. Copied from gnosis safe contracts
 ///
 /// EIP712 supports several forms of domain binding, but only veriifer and
@@ -35,6 +53,26 @@ pub static DOMAIN_SEPARATOR_TYPEHASH: Lazy<H256> = Lazy::new(|| {
         .unwrap()
 });
 
+/// Returns the current TOTP used by the service to authorize delegate
+/// management and proposal deletion. This is the number of hours elapsed
+/// since the unix epoch.
+pub fn totp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system clock before unix epoch")
+        .as_secs()
+        / 3600
+}
+
+/// EIP-712 typehash of the proposal deletion authorization used by the
+/// service
+///
+/// keccak256(
+///     "DeleteRequest(bytes32 safeTxHash,uint256 totp)"
+/// );
+pub static DELETE_REQUEST_TYPEHASH: Lazy<H256> =
+    Lazy::new(|| keccak256("DeleteRequest(bytes32 safeTxHash,uint256 totp)").into());
+
 pub(crate) fn default_empty_bytes_ref<S>(
     bytes: &Option<&Bytes>,
     serializer: S,
@@ -105,12 +143,10 @@ impl<'de> Deserialize<'de> for Operations {
     where
         D: serde::Deserializer<'de>,
     {
-        u8::deserialize(deserializer).map(|num| {
-            if num == 2 {
-                Operations::DelegateCall
-            } else {
-                Operations::Call
-            }
+        // lenient, as before. 2 was historically treated as a delegatecall
+        u8::deserialize(deserializer).map(|num| match num {
+            1 | 2 => Operations::DelegateCall,
+            _ => Operations::Call,
         })
     }
 }
@@ -329,16 +365,24 @@ impl<'de> serde::Deserialize<'de> for DecimalU256 {
     where
         D: serde::Deserializer<'de>,
     {
-        String::deserialize(deserializer)?
-            .parse()
-            .map_err(serde::de::Error::custom)
+        // the API uses both decimal strings and JSON numbers
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum StrOrNum {
+            Str(String),
+            Num(u64),
+        }
+
+        match StrOrNum::deserialize(deserializer)? {
+            StrOrNum::Str(s) => s.parse().map_err(serde::de::Error::custom),
+            StrOrNum::Num(n) => Ok(U256::from(n).into()),
+        }
     }
 }
 
 pub(crate) mod dec_u256_ser {
     use super::*;
 
-    #[allow(dead_code)]
     pub(crate) fn serialize<S>(u: &U256, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
@@ -434,4 +478,20 @@ mod test {
 
         let _: super::ApiResponse<SafeInfoResponse> = serde_json::from_str(resp).unwrap();
     }
+
+    #[test]
+    fn it_deserializes_operations_leniently() {
+        use super::Operations;
+
+        let ops: Vec<Operations> = serde_json::from_str("[0, 1, 2, 3]").unwrap();
+        assert_eq!(
+            ops,
+            vec![
+                Operations::Call,
+                Operations::DelegateCall,
+                Operations::DelegateCall,
+                Operations::Call,
+            ]
+        );
+    }
 }
@@ -0,0 +1,25 @@
+use ethers::types::{Signature, H256};
+use reqwest::Url;
+
+/// Add an owner confirmation to a proposed transaction
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct ConfirmationRequest {
+    #[serde(with = "crate::rpc::propose::rsv_sig_ser")]
+    pub(crate) signature: Signature,
+}
+
+impl From<Signature> for ConfirmationRequest {
+    fn from(signature: Signature) -> Self {
+        Self { signature }
+    }
+}
+
+impl ConfirmationRequest {
+    /// Return the URL to which to dispatch this request
+    pub fn url(root: &Url, safe_tx_hash: H256) -> Url {
+        let path = format!("api/v1/multisig-transactions/{safe_tx_hash:?}/confirmations/");
+        let mut url = root.clone();
+        url.set_path(&path);
+        url
+    }
+}
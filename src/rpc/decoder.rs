@@ -0,0 +1,39 @@
+use ethers::types::{Address, Bytes};
+use reqwest::Url;
+
+use super::{msig_history::DecodedData, propose::MetaTransactionData};
+
+/// Decode calldata via the API
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct DataDecoderRequest {
+    pub(crate) data: Bytes,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) to: Option<Address>,
+}
+
+impl DataDecoderRequest {
+    /// Instantiate a request. `to` helps the API select the target's ABI
+    pub fn new(data: Bytes, to: Option<Address>) -> Self {
+        Self { data, to }
+    }
+
+    /// Return the URL to which to dispatch this request
+    pub fn url(root: &Url) -> Url {
+        let mut url = root.clone();
+        url.set_path("api/v1/data-decoder/");
+        url
+    }
+}
+
+impl From<&MetaTransactionData> for DataDecoderRequest {
+    fn from(tx: &MetaTransactionData) -> Self {
+        Self {
+            data: tx.data.clone().unwrap_or_default(),
+            to: Some(tx.to.into()),
+        }
+    }
+}
+
+/// Response of the data decoder endpoint. Same as a multisig transaction's
+/// `data_decoded`
+pub type DataDecoderResponse = DecodedData;
@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+
+use ethers::{signers::Signer, types::Address, types::Signature};
+use reqwest::Url;
+
+use crate::{client::ClientResult, SafeClient};
+
+use super::common::{totp, ChecksumAddress, Paginated};
+
+/// Response for delegate list requests
+pub type DelegatesResponse = Paginated<DelegateResponse>;
+
+/// Returns the message a delegator must sign to add or remove `delegate`.
+///
+/// The service expects `checksum(delegate) + str(totp)`, signed with
+/// `eth_sign` (EIP-191 prefixed)
+pub fn delegate_message(delegate: Address, totp: u64) -> String {
+    format!("{}{}", ethers::utils::to_checksum(&delegate, None), totp)
+}
+
+/// Delegates list request
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct DelegatesRequest;
+
+impl DelegatesRequest {
+    /// Return the URL to which to dispatch this request
+    pub fn url(root: &Url) -> Url {
+        let mut url = root.clone();
+        url.set_path("api/v1/delegates/");
+        url
+    }
+}
+
+/// A delegate registered with the service
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DelegateResponse {
+    /// The Safe for which the delegate may propose. `None` if the delegate
+    /// may propose for every Safe the delegator owns
+    #[serde(default)]
+    pub safe: Option<Address>,
+    /// The delegate's address
+    pub delegate: Address,
+    /// The owner that registered the delegate
+    pub delegator: Address,
+    /// Human-readable label
+    #[serde(default)]
+    pub label: String,
+}
+
+/// Register a delegate, authorized by a delegator signature
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddDelegateRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) safe: Option<ChecksumAddress>,
+    pub(crate) delegate: ChecksumAddress,
+    pub(crate) delegator: ChecksumAddress,
+    #[serde(with = "crate::rpc::propose::rsv_sig_ser")]
+    pub(crate) signature: Signature,
+    pub(crate) label: String,
+}
+
+impl AddDelegateRequest {
+    /// Return the URL to which to dispatch this request
+    pub fn url(root: &Url) -> Url {
+        DelegatesRequest::url(root)
+    }
+
+    /// Sign a delegate registration with the delegator's signer. If `safe` is
+    /// `None`, the delegate may propose for every Safe the delegator owns
+    pub async fn sign<S: Signer>(
+        signer: &S,
+        safe: Option<Address>,
+        delegate: Address,
+        label: impl Into<String>,
+    ) -> Result<Self, S::Error> {
+        let signature = signer
+            .sign_message(delegate_message(delegate, totp()))
+            .await?;
+        Ok(Self {
+            safe: safe.map(Into::into),
+            delegate: delegate.into(),
+            delegator: signer.address().into(),
+            signature,
+            label: label.into(),
+        })
+    }
+}
+
+/// Remove a delegate, authorized by a delegator signature
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoveDelegateRequest {
+    pub(crate) delegate: ChecksumAddress,
+    pub(crate) delegator: ChecksumAddress,
+    #[serde(with = "crate::rpc::propose::rsv_sig_ser")]
+    pub(crate) signature: Signature,
+}
+
+impl RemoveDelegateRequest {
+    /// Return the URL to which to dispatch this request
+    pub fn url(root: &Url, delegate: impl Into<ChecksumAddress>) -> Url {
+        let path = format!("api/v1/delegates/{}/", delegate.into());
+        let mut url = root.clone();
+        url.set_path(&path);
+        url
+    }
+
+    /// Sign a delegate removal with the delegator's signer
+    pub async fn sign<S: Signer>(signer: &S, delegate: Address) -> Result<Self, S::Error> {
+        let signature = signer
+            .sign_message(delegate_message(delegate, totp()))
+            .await?;
+        Ok(Self {
+            delegate: delegate.into(),
+            delegator: signer.address().into(),
+            signature,
+        })
+    }
+}
+
+/// Delegates request with filters
+#[derive(Clone, serde::Serialize)]
+pub struct DelegatesFilters<'a> {
+    #[serde(flatten)]
+    pub(crate) filters: HashMap<&'static str, String>,
+    #[serde(skip)]
+    pub(crate) client: &'a SafeClient,
+}
+
+impl<'a> DelegatesFilters<'a> {
+    /// Dispatch the request to the API, querying delegates from the API
+    pub async fn query(self) -> ClientResult<DelegatesResponse> {
+        self.client.filtered_delegates(self.filters).await
+    }
+
+    /// Return the URL to which to dispatch this request
+    pub fn url(root: &Url) -> Url {
+        DelegatesRequest::url(root)
+    }
+
+    /// Instantiate from a client
+    pub(crate) fn new(client: &'a SafeClient) -> Self {
+        Self {
+            filters: Default::default(),
+            client,
+        }
+    }
+
+    fn insert_address(&mut self, k: &'static str, address: Address) {
+        self.filters
+            .insert(k, ethers::utils::to_checksum(&address, None));
+    }
+
+    /// Filter delegates by Safe
+    pub fn safe(mut self, safe_address: Address) -> Self {
+        self.insert_address("safe", safe_address);
+        self
+    }
+
+    /// Filter by delegate address
+    pub fn delegate(mut self, delegate: Address) -> Self {
+        self.insert_address("delegate", delegate);
+        self
+    }
+
+    /// Filter by delegator address
+    pub fn delegator(mut self, delegator: Address) -> Self {
+        self.insert_address("delegator", delegator);
+        self
+    }
+
+    /// Filter by label
+    pub fn label(mut self, label: String) -> Self {
+        self.filters.insert("label", label);
+        self
+    }
+
+    /// Specify page limit. If more results than limit are returned, results in
+    /// a paginated response
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.filters.insert("limit", limit.to_string());
+        self
+    }
+
+    /// Specify offset in results. Used in pagination, not recommended to be
+    /// specified manually
+    pub fn offset(mut self, offset: u64) -> Self {
+        self.filters.insert("offset", offset.to_string());
+        self
+    }
+
+    /// Converts to a URL with query string
+    pub fn to_url(self) -> Url {
+        let mut url = Self::url(self.client.url());
+        url.query_pairs_mut().extend_pairs(self.filters.iter());
+        url
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ethers::{signers::LocalWallet, utils::hash_message};
+
+    use super::*;
+
+    #[test]
+    fn it_formats_delegate_messages() {
+        let delegate: Address = "0x425249cf0f2f91f488e24cf7b1aa3186748f7516"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            delegate_message(delegate, 466_000),
+            "0x425249Cf0F2f91f488E24cF7B1AA3186748f7516466000"
+        );
+    }
+
+    #[tokio::test]
+    async fn it_signs_delegate_messages() {
+        let signer: LocalWallet =
+            "1c3a7cdd2270579847aaec11680312cbf4d3c36886232b413ab6529593228ec2"
+                .parse()
+                .unwrap();
+        let delegate = Address::repeat_byte(0x22);
+
+        // the totp may roll over between signing and checking
+        let recovers = |signature: &Signature| {
+            let now = totp();
+            [now, now - 1].into_iter().any(|totp| {
+                signature
+                    .recover(hash_message(delegate_message(delegate, totp)))
+                    .ok()
+                    == Some(signer.address())
+            })
+        };
+
+        let add = AddDelegateRequest::sign(&signer, None, delegate, "bot")
+            .await
+            .unwrap();
+        assert!(recovers(&add.signature));
+        let json = serde_json::to_value(&add).unwrap();
+        assert_eq!(json["safe"], serde_json::Value::Null);
+        assert_eq!(
+            json["delegator"],
+            format!("{}", ChecksumAddress::from(signer.address()))
+        );
+        assert_eq!(json["label"], "bot");
+
+        let remove = RemoveDelegateRequest::sign(&signer, delegate)
+            .await
+            .unwrap();
+        assert!(recovers(&remove.signature));
+    }
+
+    #[tokio::test]
+    async fn it_checks_only_the_queried_delegate() {
+        use crate::rpc::mock::{page, safe_info, MockService};
+
+        let safe = Address::repeat_byte(0x5a);
+        let owner = Address::repeat_byte(0xa);
+        let (bot, stranger) = (Address::repeat_byte(0xb), Address::repeat_byte(0xc));
+        let service = MockService::start(move |path, _| {
+            if path == "/api/v1/delegates/" {
+                // as if unfiltered: every delegate of the Safe
+                let delegation = serde_json::json!({
+                    "safe": safe,
+                    "delegate": bot,
+                    "delegator": owner,
+                    "label": "bot",
+                });
+                (200, page(vec![delegation]))
+            } else {
+                (200, safe_info(safe, 0, 1, &[owner]))
+            }
+        })
+        .await;
+        let client = service.client(1);
+
+        assert!(client.is_delegate(safe, bot).await.unwrap());
+        assert!(!client.is_delegate(safe, stranger).await.unwrap());
+        let query = format!("delegate={}", ethers::utils::to_checksum(&stranger, None));
+        assert!(service.requests().iter().any(|r| r.ends_with(&query)));
+    }
+}
@@ -7,7 +7,8 @@ use super::common::Operations;
 /// Estimates `safe_tx_gas` for a proposed msig txn
 pub struct EstimateRequest<'a> {
     pub(crate) to: Address,
-    pub(crate) value: u64,
+    #[serde(serialize_with = "crate::rpc::common::dec_u256_ser::serialize")]
+    pub(crate) value: U256,
     #[serde(serialize_with = "crate::rpc::common::default_empty_bytes_ref")]
     pub(crate) data: Option<&'a Bytes>,
     pub(crate) operation: Operations,
@@ -28,8 +29,10 @@ impl<'a> EstimateRequest<'a> {
 
 /// Response of the estimate endpoint
 #[derive(Debug, Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct EstimateResponse {
     /// The amount of gas estimated
+    #[serde(with = "crate::rpc::common::dec_u256_ser")]
     pub safe_tx_gas: U256,
 }
 
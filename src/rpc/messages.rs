@@ -0,0 +1,363 @@
+use std::convert::Infallible;
+
+use chrono::{DateTime, Utc};
+use ethers::{
+    abi::{self, Tokenize},
+    signers::Signer,
+    types::{
+        transaction::eip712::{EIP712Domain, Eip712, Eip712Error, TypedData},
+        Address, Bytes, Signature, H256,
+    },
+    utils::{hash_message, keccak256},
+};
+use reqwest::Url;
+
+use crate::contracts::{pack_owner_signatures, SignatureError};
+
+use super::common::{Paginated, SAFE_MESSAGE_TYPEHASH};
+
+/// The content of a Safe message: a text message signed per EIP-191, or
+/// EIP-712 typed data
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum SafeMessage {
+    /// A text message, as signed by `personal_sign`
+    Text(String),
+    /// EIP-712 typed data, as signed by `eth_signTypedData`
+    TypedData(Box<TypedData>),
+}
+
+impl From<String> for SafeMessage {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<&str> for SafeMessage {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_owned())
+    }
+}
+
+impl From<TypedData> for SafeMessage {
+    fn from(typed: TypedData) -> Self {
+        Self::TypedData(Box::new(typed))
+    }
+}
+
+impl SafeMessage {
+    /// The message hash verified by `isValidSignature(bytes32,bytes)`: the
+    /// EIP-191 hash of a text message, or the EIP-712 hash of typed data
+    pub fn hash(&self) -> Result<H256, Eip712Error> {
+        match self {
+            SafeMessage::Text(text) => Ok(hash_message(text)),
+            SafeMessage::TypedData(typed) => typed.encode_eip712().map(Into::into),
+        }
+    }
+
+    /// The Safe's EIP-712 hash of the message, signed by owners. This is the
+    /// message hash used by the API
+    pub fn safe_message_hash(
+        &self,
+        safe_address: Address,
+        chain_id: u64,
+    ) -> Result<H256, Eip712Error> {
+        Ok(SafeMessageEip712::new(safe_address, chain_id, self.hash()?).encode())
+    }
+
+    /// Sign the Safe message hash
+    pub(crate) async fn sign<S: Signer>(
+        safe_address: Address,
+        chain_id: u64,
+        message_hash: H256,
+        signer: &S,
+    ) -> Result<Signature, S::Error> {
+        signer
+            .sign_typed_data(&SafeMessageEip712::new(
+                safe_address,
+                chain_id,
+                message_hash,
+            ))
+            .await
+    }
+}
+
+/// Internal type to support 712 trait impl for Safe messages. Matches
+/// `getMessageHashForSafe` of the Safe's fallback handler
+#[derive(Clone, Debug)]
+struct SafeMessageEip712 {
+    safe_address: Address,
+    chain_id: u64,
+    message_hash: H256,
+}
+
+impl SafeMessageEip712 {
+    fn new(safe_address: Address, chain_id: u64, message_hash: H256) -> Self {
+        Self {
+            safe_address,
+            chain_id,
+            message_hash,
+        }
+    }
+
+    fn encode(&self) -> H256 {
+        self.encode_eip712().expect("infallible").into()
+    }
+}
+
+impl Eip712 for SafeMessageEip712 {
+    type Error = Infallible;
+
+    fn domain(&self) -> Result<EIP712Domain, Self::Error> {
+        Ok(EIP712Domain {
+            name: None,
+            version: None,
+            chain_id: Some(self.chain_id.into()),
+            verifying_contract: Some(self.safe_address),
+            salt: None,
+        })
+    }
+
+    fn type_hash() -> Result<[u8; 32], Self::Error> {
+        Ok(From::from(*SAFE_MESSAGE_TYPEHASH))
+    }
+
+    fn struct_hash(&self) -> Result<[u8; 32], Self::Error> {
+        // the signed message is the 32-byte hash, so `keccak256(message)`
+        // hashes it again
+        let message = H256::from(keccak256(self.message_hash));
+        Ok(keccak256(abi::encode(
+            &(*SAFE_MESSAGE_TYPEHASH, message).into_tokens(),
+        )))
+    }
+}
+
+/// Propose a Safe message, with the proposer's signature
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageRequest {
+    /// The message
+    pub message: SafeMessage,
+    /// ID of the Safe app requesting the signature, if any
+    pub safe_app_id: Option<u64>,
+    /// Signature of the Safe message hash by an owner
+    #[serde(with = "crate::rpc::propose::rsv_sig_ser")]
+    pub signature: Signature,
+}
+
+impl CreateMessageRequest {
+    /// Return the URL to which to dispatch this request
+    pub fn url(root: &Url, safe_address: Address) -> Url {
+        SafeMessagesRequest::url(root, safe_address)
+    }
+}
+
+/// Safe messages request
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct SafeMessagesRequest;
+
+impl SafeMessagesRequest {
+    /// Return the URL to which to dispatch this request
+    pub fn url(root: &Url, safe_address: Address) -> Url {
+        let path = format!(
+            "api/v1/safes/{}/messages/",
+            ethers::utils::to_checksum(&safe_address, None)
+        );
+        let mut url = root.clone();
+        url.set_path(&path);
+        url
+    }
+}
+
+/// Add an owner signature to a Safe message
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct MessageSignatureRequest {
+    /// Signature of the Safe message hash by an owner
+    #[serde(with = "crate::rpc::propose::rsv_sig_ser")]
+    pub signature: Signature,
+}
+
+impl From<Signature> for MessageSignatureRequest {
+    fn from(signature: Signature) -> Self {
+        Self { signature }
+    }
+}
+
+impl MessageSignatureRequest {
+    /// Return the URL to which to dispatch this request
+    pub fn url(root: &Url, message_hash: H256) -> Url {
+        let path = format!("api/v1/messages/{message_hash:?}/signatures/");
+        let mut url = root.clone();
+        url.set_path(&path);
+        url
+    }
+}
+
+/// Safe message request
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct MessageRequest;
+
+impl MessageRequest {
+    /// Return the URL to which to dispatch this request
+    pub fn url(root: &Url, message_hash: H256) -> Url {
+        let path = format!("api/v1/messages/{message_hash:?}/");
+        let mut url = root.clone();
+        url.set_path(&path);
+        url
+    }
+}
+
+/// Owner signature of a Safe message
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageConfirmationResponse {
+    /// Time the signature was submitted
+    pub created: DateTime<Utc>,
+    /// Time the signature was modified
+    pub modified: DateTime<Utc>,
+    /// The owner
+    pub owner: Address,
+    /// The signature, in RSV format
+    pub signature: Bytes,
+    /// The signature type, e.g. `EOA` or `ETH_SIGN`
+    pub signature_type: String,
+}
+
+/// A Safe message, as stored by the API
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SafeMessageResponse {
+    /// Time the message was proposed
+    pub created: DateTime<Utc>,
+    /// Time the message was modified
+    pub modified: DateTime<Utc>,
+    /// The Safe
+    pub safe: Address,
+    /// The Safe message hash, signed by owners
+    pub message_hash: H256,
+    /// The message
+    pub message: SafeMessage,
+    /// The owner that proposed the message
+    pub proposed_by: Address,
+    /// ID of the Safe app that requested the signature, if any
+    #[serde(default)]
+    pub safe_app_id: Option<u64>,
+    /// Owner signatures
+    pub confirmations: Vec<MessageConfirmationResponse>,
+    /// The combined signature prepared by the API, once enough owners signed
+    #[serde(default)]
+    pub prepared_signature: Option<Bytes>,
+}
+
+impl SafeMessageResponse {
+    /// The signatures of `threshold` current owners combined for
+    /// `isValidSignature`. Signatures by removed owners are ignored. See
+    /// [`pack_owner_signatures`]
+    pub fn combined_signature(
+        &self,
+        owners: &[Address],
+        threshold: u32,
+    ) -> Result<Bytes, SignatureError> {
+        pack_owner_signatures(
+            self.confirmations
+                .iter()
+                .map(|c| (c.owner, c.signature.clone())),
+            owners,
+            threshold,
+        )
+    }
+
+    /// True if at least `threshold` current owners signed
+    pub fn is_fully_signed(&self, owners: &[Address], threshold: u32) -> bool {
+        let mut signers: Vec<_> = self
+            .confirmations
+            .iter()
+            .map(|c| c.owner)
+            .filter(|owner| owners.contains(owner))
+            .collect();
+        signers.sort();
+        signers.dedup();
+        signers.len() >= threshold as usize
+    }
+}
+
+/// Safe messages endpoint response
+pub type SafeMessagesResponse = Paginated<SafeMessageResponse>;
+
+#[cfg(test)]
+mod test {
+    use ethers::signers::LocalWallet;
+
+    use super::*;
+    use crate::rpc::propose::SafeTransactionData;
+
+    #[test]
+    fn it_hashes_safe_messages() {
+        assert_eq!(
+            H256::from(keccak256("SafeMessage(bytes message)")),
+            *SAFE_MESSAGE_TYPEHASH
+        );
+
+        // the domain is the Safe's, as used for transactions
+        let safe = Address::repeat_byte(0x5a);
+        let message = SafeMessageEip712::new(safe, 5, H256::zero());
+        assert_eq!(
+            message.domain_separator().unwrap(),
+            SafeTransactionData::rejection(safe, 0)
+                .eip712(safe, 5)
+                .domain_separator()
+                .unwrap()
+        );
+
+        let text = SafeMessage::from("Sign in to example.com");
+        assert_eq!(text.hash().unwrap(), hash_message("Sign in to example.com"));
+        assert_ne!(
+            text.safe_message_hash(safe, 5).unwrap(),
+            text.safe_message_hash(safe, 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn it_counts_current_owners() {
+        let confirmation = |owner: Address| {
+            serde_json::json!({
+                "created": "2023-01-01T00:00:00Z",
+                "modified": "2023-01-01T00:00:00Z",
+                "owner": owner,
+                "signature": Bytes::from([vec![0xaa; 64], vec![27]].concat()),
+                "signatureType": "EOA",
+            })
+        };
+        let (owner, removed) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let message: SafeMessageResponse = serde_json::from_value(serde_json::json!({
+            "created": "2023-01-01T00:00:00Z",
+            "modified": "2023-01-01T00:00:00Z",
+            "safe": Address::repeat_byte(0x5a),
+            "messageHash": H256::zero(),
+            "message": "hello",
+            "proposedBy": owner,
+            "confirmations": [confirmation(owner), confirmation(removed)],
+        }))
+        .unwrap();
+
+        assert!(message.is_fully_signed(&[owner], 1));
+        assert!(!message.is_fully_signed(&[owner], 2));
+        assert_eq!(message.combined_signature(&[owner], 1).unwrap().len(), 65);
+        assert!(message.combined_signature(&[owner], 2).is_err());
+    }
+
+    #[tokio::test]
+    async fn it_signs_safe_messages() {
+        let signer: LocalWallet =
+            "1c3a7cdd2270579847aaec11680312cbf4d3c36886232b413ab6529593228ec2"
+                .parse()
+                .unwrap();
+        let safe = Address::repeat_byte(0x5a);
+        let message = SafeMessage::from("hello");
+        let hash = message.safe_message_hash(safe, 1).unwrap();
+        let signature = SafeMessage::sign(safe, 1, message.hash().unwrap(), &signer)
+            .await
+            .unwrap();
+        assert_eq!(signature.recover(hash).unwrap(), signer.address());
+    }
+}
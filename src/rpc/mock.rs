@@ -0,0 +1,134 @@
+use std::sync::{Arc, Mutex};
+
+use ethers::types::Address;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{networks::TxService, SafeClient};
+
+type Handler = dyn Fn(&str, &str) -> (u16, serde_json::Value) + Send + Sync;
+
+/// A local stand-in for the transaction service. Responds to each request
+/// via a handler of its path and query, and records the request targets
+/// actually dispatched
+pub(crate) struct MockService {
+    url: &'static str,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockService {
+    /// Serve requests with `handler(path, query) -> (status, body)`. A null
+    /// body is sent empty
+    pub(crate) async fn start(
+        handler: impl Fn(&str, &str) -> (u16, serde_json::Value) + Send + Sync + 'static,
+    ) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let requests: Arc<Mutex<Vec<String>>> = Default::default();
+
+        let handler: Arc<Handler> = Arc::new(handler);
+        let log = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(respond(stream, handler.clone(), log.clone()));
+            }
+        });
+
+        Self {
+            url: Box::leak(url.into_boxed_str()),
+            requests,
+        }
+    }
+
+    /// A client of this service, for `chain_id`
+    pub(crate) fn client(&self, chain_id: u64) -> SafeClient {
+        SafeClient::new(TxService::new(self.url, chain_id))
+    }
+
+    /// Request targets dispatched so far, as `METHOD /path?query`
+    pub(crate) fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// A single page of results
+pub(crate) fn page(results: Vec<serde_json::Value>) -> serde_json::Value {
+    serde_json::json!({
+        "count": results.len(),
+        "next": null,
+        "previous": null,
+        "results": results,
+    })
+}
+
+/// Safe info, as returned by the API
+pub(crate) fn safe_info(
+    safe: Address,
+    nonce: u64,
+    threshold: u32,
+    owners: &[Address],
+) -> serde_json::Value {
+    serde_json::json!({
+        "address": safe,
+        "nonce": nonce,
+        "threshold": threshold,
+        "owners": owners,
+        "masterCopy": "0x3E5c63644E683549055b9Be8653de26E0B4CD36E",
+        "modules": [],
+        "fallbackHandler": Address::zero(),
+        "guard": Address::zero(),
+        "version": "1.3.0+L2",
+    })
+}
+
+async fn respond(mut stream: TcpStream, handler: Arc<Handler>, log: Arc<Mutex<Vec<String>>>) {
+    let mut buf = vec![];
+    let mut chunk = [0u8; 4096];
+    let (head, body_start) = loop {
+        let n = stream.read(&mut chunk).await.unwrap();
+        if n == 0 {
+            return;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(i) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break (String::from_utf8_lossy(&buf[..i]).into_owned(), i + 4);
+        }
+    };
+
+    // drain the body, so the client sees the response rather than a reset
+    let content_length = head
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, v)| v.trim().parse::<usize>().ok())
+        .unwrap_or_default();
+    while buf.len() < body_start + content_length {
+        let n = stream.read(&mut chunk).await.unwrap();
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+
+    let mut request_line = head.lines().next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default();
+    let target = request_line.next().unwrap_or_default();
+    log.lock().unwrap().push(format!("{method} {target}"));
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let (status, body) = handler(path, query);
+    // null for an empty body, as for successful POSTs
+    let body = if body.is_null() {
+        String::new()
+    } else {
+        body.to_string()
+    };
+    let response = format!(
+        "HTTP/1.1 {status} MOCK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
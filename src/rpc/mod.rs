@@ -18,5 +18,24 @@ pub mod msig_history;
 /// Propose Safe msig transactions
 pub mod propose;
 
+/// Confirm proposed Safe msig transactions
+pub mod confirm;
+
+/// Off-chain Safe messages, signed by owners for EIP-1271
+pub mod messages;
+
+/// Decode calldata via the API
+pub mod decoder;
+
 /// Estimates `safe_tx_gas` for an msig txn
 pub mod estimate;
+
+/// Manage delegates allowed to propose on behalf of owners
+pub mod delegates;
+
+/// Pending transaction queue, grouped by nonce
+pub mod queue;
+
+/// Local stand-in for the transaction service, for tests
+#[cfg(test)]
+pub(crate) mod mock;
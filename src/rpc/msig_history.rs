@@ -1,13 +1,24 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 use async_stream::stream;
-use ethers::types::{Address, Bytes, H256, U256};
+use chrono::{DateTime, SecondsFormat, Utc};
+use ethers::{
+    abi::{param_type::Reader, ParamType},
+    types::{Address, Bytes, H256, I256, U256},
+};
 use reqwest::Url;
 use serde::Serialize;
 
-use crate::{client::ClientResult, SafeClient};
+use crate::{
+    client::ClientResult,
+    contracts::{pack_owner_signatures, SignatureError},
+    SafeClient,
+};
 
-use super::common::{Operations, Paginated};
+use super::{
+    common::{Operations, Paginated},
+    propose::ProposalOrigin,
+};
 
 /// Response for multisig history requests
 pub type MsigHistoryResponse = Paginated<MsigTxResponse>;
@@ -26,19 +37,205 @@ impl MsigTxRequest {
     }
 }
 
-/// Decoded function call parameter
+/// A decoded parameter value, typed according to the parameter's solidity
+/// type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParameterValue {
+    /// `address`
+    Address(Address),
+    /// `uint<M>`
+    Uint(U256),
+    /// `int<M>`
+    Int(I256),
+    /// `bool`
+    Bool(bool),
+    /// `bytes` or `bytes<M>`
+    Bytes(Bytes),
+    /// `string`
+    String(String),
+    /// `T[]` or `T[k]`
+    Array(Vec<ParameterValue>),
+    /// `(T1,T2,...)`
+    Tuple(Vec<ParameterValue>),
+    /// A value that could not be decoded according to its type, as returned
+    /// by the API
+    Raw(serde_json::Value),
+}
+
+impl ParameterValue {
+    /// Decode a value as returned by the API, according to its solidity type.
+    /// Falls back to [`ParameterValue::Raw`] if the type or value cannot be
+    /// parsed
+    pub fn decode(param_type: &str, value: serde_json::Value) -> Self {
+        Reader::read(param_type)
+            .ok()
+            .and_then(|kind| Self::decode_as(&kind, &value))
+            .unwrap_or(ParameterValue::Raw(value))
+    }
+
+    fn decode_as(kind: &ParamType, value: &serde_json::Value) -> Option<Self> {
+        use serde_json::Value;
+
+        Some(match (kind, value) {
+            (ParamType::Address, Value::String(s)) => ParameterValue::Address(s.parse().ok()?),
+            (ParamType::Uint(_), Value::String(s)) => {
+                ParameterValue::Uint(U256::from_dec_str(s).ok()?)
+            }
+            (ParamType::Uint(_), Value::Number(n)) => ParameterValue::Uint(n.as_u64()?.into()),
+            (ParamType::Int(_), Value::String(s)) => {
+                ParameterValue::Int(I256::from_dec_str(s).ok()?)
+            }
+            (ParamType::Int(_), Value::Number(n)) => ParameterValue::Int(n.as_i64()?.into()),
+            (ParamType::Bool, Value::Bool(b)) => ParameterValue::Bool(*b),
+            (ParamType::Bool, Value::String(s)) => match s.to_ascii_lowercase().as_str() {
+                "true" => ParameterValue::Bool(true),
+                "false" => ParameterValue::Bool(false),
+                _ => return None,
+            },
+            (ParamType::Bytes | ParamType::FixedBytes(_), Value::String(s)) => {
+                ParameterValue::Bytes(s.parse().ok()?)
+            }
+            (ParamType::String, Value::String(s)) => ParameterValue::String(s.clone()),
+            (ParamType::Array(inner) | ParamType::FixedArray(inner, _), Value::Array(items)) => {
+                ParameterValue::Array(
+                    items
+                        .iter()
+                        .map(|item| Self::decode_as(inner, item))
+                        .collect::<Option<_>>()?,
+                )
+            }
+            (ParamType::Tuple(kinds), Value::Array(items)) if kinds.len() == items.len() => {
+                ParameterValue::Tuple(
+                    kinds
+                        .iter()
+                        .zip(items.iter())
+                        .map(|(kind, item)| Self::decode_as(kind, item))
+                        .collect::<Option<_>>()?,
+                )
+            }
+            _ => return None,
+        })
+    }
+
+    /// The address, if this is an address value
+    pub fn as_address(&self) -> Option<Address> {
+        match self {
+            ParameterValue::Address(a) => Some(*a),
+            _ => None,
+        }
+    }
+
+    /// The integer, if this is an unsigned integer value
+    pub fn as_uint(&self) -> Option<U256> {
+        match self {
+            ParameterValue::Uint(u) => Some(*u),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ParameterValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn list(f: &mut std::fmt::Formatter<'_>, items: &[ParameterValue]) -> std::fmt::Result {
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{item}")?;
+            }
+            Ok(())
+        }
+
+        match self {
+            ParameterValue::Address(a) => write!(f, "{}", ethers::utils::to_checksum(a, None)),
+            ParameterValue::Uint(u) => write!(f, "{u}"),
+            ParameterValue::Int(i) => write!(f, "{i}"),
+            ParameterValue::Bool(b) => write!(f, "{b}"),
+            ParameterValue::Bytes(b) => write!(f, "{b}"),
+            ParameterValue::String(s) => write!(f, "{s:?}"),
+            ParameterValue::Array(items) => {
+                write!(f, "[")?;
+                list(f, items)?;
+                write!(f, "]")
+            }
+            ParameterValue::Tuple(items) => {
+                write!(f, "(")?;
+                list(f, items)?;
+                write!(f, ")")
+            }
+            ParameterValue::Raw(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+/// A transaction decoded from a multiSend `transactions` parameter
 #[derive(Debug, Clone, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct DecodedTransaction {
+    /// CALL or DELEGATECALL
+    pub operation: Operations,
+    /// Target of the transaction
+    pub to: Address,
+    /// Native asset value included in the transaction
+    #[serde(default, with = "crate::rpc::common::dec_u256_ser")]
+    pub value: U256,
+    /// Data payload sent to the target
+    #[serde(default)]
+    pub data: Option<Bytes>,
+    /// Decoded data payload, if the API recognizes it
+    #[serde(default)]
+    pub data_decoded: Option<DecodedData>,
+}
+
+/// Decoded function call parameter
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(from = "RawParameter")]
 pub struct Parameter {
     /// Parameter name
     pub name: String,
     /// Solidity type of parameter
-    #[serde(rename = "type")]
     pub param_type: String,
-    // TODO
-    // /// Parameter value
-    // pub value: String,
-    // pub value_decoded: Vec<String>
+    /// Parameter value
+    pub value: ParameterValue,
+    /// Transactions nested in the parameter, for multiSend `transactions`
+    pub value_decoded: Option<Vec<DecodedTransaction>>,
+    /// `valueDecoded` as returned by the API, if it could not be decoded as
+    /// transactions
+    pub value_decoded_raw: Option<serde_json::Value>,
+}
+
+/// Parameter as returned by the API, before typing its value
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawParameter {
+    name: String,
+    #[serde(rename = "type")]
+    param_type: String,
+    #[serde(default)]
+    value: serde_json::Value,
+    // only nested transactions are typed, as other decodings are
+    // unspecified
+    #[serde(default)]
+    value_decoded: Option<serde_json::Value>,
+}
+
+impl From<RawParameter> for Parameter {
+    fn from(raw: RawParameter) -> Self {
+        let (value_decoded, value_decoded_raw) = match raw.value_decoded {
+            Some(v) => match serde_json::from_value(v.clone()) {
+                Ok(txns) => (Some(txns), None),
+                Err(_) => (None, Some(v)),
+            },
+            None => (None, None),
+        };
+        Self {
+            value: ParameterValue::decode(&raw.param_type, raw.value),
+            name: raw.name,
+            param_type: raw.param_type,
+            value_decoded,
+            value_decoded_raw,
+        }
+    }
 }
 
 /// Decoded function call
@@ -51,6 +248,21 @@ pub struct DecodedData {
     pub parameters: Vec<Parameter>,
 }
 
+impl DecodedData {
+    /// The parameter named `name`, if any
+    pub fn parameter(&self, name: &str) -> Option<&Parameter> {
+        self.parameters.iter().find(|p| p.name == name)
+    }
+
+    /// Transactions nested in this call, if it is a multiSend
+    pub fn nested_transactions(&self) -> impl Iterator<Item = &DecodedTransaction> {
+        self.parameters
+            .iter()
+            .filter_map(|p| p.value_decoded.as_ref())
+            .flatten()
+    }
+}
+
 /// Confirmation info for a multisig transaction
 #[derive(serde::Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -58,7 +270,7 @@ pub struct MsigConfirmationResponse {
     /// Which owner this confirmation was produced by
     pub owner: Address,
     /// Date at which the confirmation was submitted
-    pub submission_date: String,
+    pub submission_date: DateTime<Utc>,
     /// TODO: what is this?
     pub transaction_hash: Option<H256>,
     /// The signatures string, in RSV format
@@ -99,11 +311,12 @@ pub struct MsigTxResponse {
     /// Tx Nonce
     pub nonce: u64,
     /// Execution time, if executed
-    pub execution_date: Option<String>,
+    #[serde(default)]
+    pub execution_date: Option<DateTime<Utc>>,
     /// Time tx was submitted to the safe transaction service
-    pub submission_date: String,
+    pub submission_date: DateTime<Utc>,
     /// Time tx was modified
-    pub modified: String,
+    pub modified: DateTime<Utc>,
     /// Block number of confirmation (none if unconfirmed)
     #[serde(default)]
     pub block_number: Option<u32>,
@@ -112,6 +325,10 @@ pub struct MsigTxResponse {
     pub transaction_hash: Option<H256>,
     /// Safe internal tx hash, produced by EIP712
     pub safe_tx_hash: H256,
+    /// Address of the account that proposed this safe tx. `None` for txns
+    /// proposed before the service tracked proposers
+    #[serde(default)]
+    pub proposer: Option<Address>,
     /// Address of account that executed this safe tx (if executed)
     #[serde(default)]
     pub executor: Option<Address>,
@@ -137,11 +354,12 @@ pub struct MsigTxResponse {
     /// Fee used in the executing transaction. None if unexecuted
     #[serde(default, with = "crate::rpc::common::dec_u256_opt_ser")]
     pub fee: Option<U256>,
-    /// TODO: what is this?
+    /// Origin metadata set by the proposer. Usually a JSON string, see
+    /// [`MsigTxResponse::proposal_origin`]
     #[serde(default)]
-    pub origin: Option<String>, // is this correct?
-    /// Decoded data (if any)
-    /// TODO: what is this?
+    pub origin: Option<String>,
+    /// Data payload decoded by the API, if it recognizes the target's ABI.
+    /// For multiSend txns, includes the nested transactions
     #[serde(default)]
     pub data_decoded: Option<DecodedData>,
     /// Confirmations required for the transaction, if any
@@ -155,24 +373,98 @@ pub struct MsigTxResponse {
     pub signatures: Option<String>, // RSV strings, tightly packed
 }
 
+impl MsigTxResponse {
+    /// The origin metadata set by the proposer, if set & parseable
+    pub fn proposal_origin(&self) -> Option<ProposalOrigin> {
+        self.origin.as_deref().and_then(ProposalOrigin::parse)
+    }
+
+    /// True if this is a rejection: a 0-value call from the Safe to itself
+    /// with no data. See [`SafeTransactionData::rejection`]
+    ///
+    /// [`SafeTransactionData::rejection`]: crate::rpc::propose::SafeTransactionData::rejection
+    pub fn is_rejection(&self) -> bool {
+        self.to == self.safe
+            && self.value.is_zero()
+            && self.data.as_deref().unwrap_or_default().is_empty()
+            && self.operation == Operations::Call
+    }
+
+    /// The signatures of `threshold` current owners, packed for
+    /// `execTransaction`. Confirmations by removed owners are ignored. See
+    /// [`pack_owner_signatures`]
+    pub fn packed_signatures(
+        &self,
+        owners: &[Address],
+        threshold: u32,
+    ) -> Result<Bytes, SignatureError> {
+        let signatures = self
+            .confirmations
+            .iter()
+            .filter(|c| owners.contains(&c.owner))
+            .map(|c| {
+                c.signature
+                    .parse::<Bytes>()
+                    .map(|sig| (c.owner, sig))
+                    .map_err(|_| SignatureError::Malformed(c.owner))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        pack_owner_signatures(signatures, owners, threshold)
+    }
+}
+
+/// Ordering of msig history results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MsigOrdering {
+    /// Nonce ascending
+    Nonce,
+    /// Nonce descending
+    NonceDesc,
+    /// Creation time ascending
+    Created,
+    /// Creation time descending
+    CreatedDesc,
+    /// Modification time ascending
+    Modified,
+    /// Modification time descending
+    ModifiedDesc,
+}
+
+impl MsigOrdering {
+    /// The value of the `ordering` query parameter
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MsigOrdering::Nonce => "nonce",
+            MsigOrdering::NonceDesc => "-nonce",
+            MsigOrdering::Created => "created",
+            MsigOrdering::CreatedDesc => "-created",
+            MsigOrdering::Modified => "modified",
+            MsigOrdering::ModifiedDesc => "-modified",
+        }
+    }
+}
+
+impl std::fmt::Display for MsigOrdering {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// Msig History Request
 #[derive(serde::Serialize, Clone)]
 pub struct MsigHistoryFilters<'a> {
     #[serde(flatten)]
-    pub(crate) filters: HashMap<&'static str, String>,
+    pub(crate) filters: BTreeMap<&'static str, String>,
     #[serde(skip)]
     pub(crate) client: &'a SafeClient,
 }
 
 impl<'a> MsigHistoryFilters<'a> {
-    // TODO: `modified` filters
-    // TODO: Execution date & submission date
-
     // deliberately not supporting LT and GT. redundant
     const NONCE_KEYS: &'static [&'static str] = &["nonce__gte", "nonce__lte", "nonce"];
 
     // GT and GTE not supported by API for some reason
-    const VALUE_KEYS: &'static [&'static str] = &["value__gt", "value__lte", "value"];
+    const VALUE_KEYS: &'static [&'static str] = &["value__gt", "value__lt", "value"];
 
     /// Dispatch the request to the API, querying txns from the specified safe
     pub async fn query(self, safe_address: Address) -> ClientResult<MsigHistoryResponse> {
@@ -185,9 +477,26 @@ impl<'a> MsigHistoryFilters<'a> {
     ///
     /// Somewhat more expensive and brittle than required, as it uses
     /// serde_json. Using display would cause hashes and addresses to be
-    /// abbreviated `0xabcd....1234`
+    /// abbreviated `0xabcd....1234`. Strings are inserted without their JSON
+    /// quotes
     fn insert<S: Serialize>(&mut self, k: &'static str, v: S) {
-        self.filters.insert(k, serde_json::to_string(&v).unwrap());
+        let v = match serde_json::to_value(&v).unwrap() {
+            serde_json::Value::String(s) => s,
+            other => other.to_string(),
+        };
+        self.filters.insert(k, v);
+    }
+
+    /// Insert an address filter, checksummed
+    fn insert_address(&mut self, k: &'static str, address: Address) {
+        self.filters
+            .insert(k, ethers::utils::to_checksum(&address, None));
+    }
+
+    /// Insert a date filter, formatted as RFC 3339
+    fn insert_date(&mut self, k: &'static str, date: DateTime<Utc>) {
+        self.filters
+            .insert(k, date.to_rfc3339_opts(SecondsFormat::Micros, true));
     }
 
     /// Return the URL to which to dispatch this request
@@ -223,7 +532,7 @@ impl<'a> MsigHistoryFilters<'a> {
 
     /// Filter txns with `nonce >= min_nonce`
     /// Clearns any exact nonce filter
-    pub fn min_nonce(mut self, min_nonce: u32) -> Self {
+    pub fn min_nonce(mut self, min_nonce: u64) -> Self {
         self.filters.remove("nonce");
         self.insert("nonce__gte", min_nonce);
         self
@@ -231,7 +540,7 @@ impl<'a> MsigHistoryFilters<'a> {
 
     /// Filter txns with `nonce <= max_nonce`
     /// Clearns any exact nonce filter
-    pub fn max_nonce(mut self, max_nonce: u32) -> Self {
+    pub fn max_nonce(mut self, max_nonce: u64) -> Self {
         self.filters.remove("nonce");
         self.insert("nonce__lte", max_nonce);
         self
@@ -239,7 +548,7 @@ impl<'a> MsigHistoryFilters<'a> {
 
     /// Filter by exact nonce
     /// Clears any min or max nonce filter
-    pub fn nonce(mut self, nonce: u32) -> Self {
+    pub fn nonce(mut self, nonce: u64) -> Self {
         self.clear_nonces();
         self.insert("nonce", nonce);
         self
@@ -253,47 +562,107 @@ impl<'a> MsigHistoryFilters<'a> {
 
     /// Filter by target
     pub fn to(mut self, addr: Address) -> Self {
-        self.insert("safe_tx_hash", addr);
+        self.insert_address("to", addr);
+        self
+    }
+
+    /// Filter by the account that executed the tx
+    pub fn executor(mut self, executor: Address) -> Self {
+        self.insert_address("executor", executor);
         self
     }
 
-    /// Filter txns with `value <= min_value`
+    /// Filter txns with `value >= min_value`
     /// Clearns any exact value filter
-    pub fn min_value(mut self, value: u64) -> Self {
+    pub fn min_value(mut self, value: U256) -> Self {
         self.filters.remove("value");
-        self.insert("value__gt", value.saturating_sub(1));
+        self.filters
+            .insert("value__gt", value.saturating_sub(1.into()).to_string());
         self
     }
 
     /// Filter txns with `value <= max_value`
     /// Clearns any exact value filter
-    pub fn max_value(mut self, value: u64) -> Self {
+    pub fn max_value(mut self, value: U256) -> Self {
         self.filters.remove("value");
-        self.insert("value__lt", value.saturating_add(1));
+        self.filters
+            .insert("value__lt", value.saturating_add(1.into()).to_string());
         self
     }
 
     /// Filter by exact value
     /// Clears any min or max value filter
-    pub fn value(mut self, value: u64) -> Self {
+    pub fn value(mut self, value: U256) -> Self {
         self.clear_values();
-        self.insert("value", value);
+        self.filters.insert("value", value.to_string());
         self
     }
 
-    /// Filter by execution status
-    ///
-    /// TODO: what are the acceptable values here? Should this be an enum?
+    /// Filter txns modified at or after `date`
+    pub fn modified_after(mut self, date: DateTime<Utc>) -> Self {
+        self.insert_date("modified__gte", date);
+        self
+    }
+
+    /// Filter txns modified at or before `date`
+    pub fn modified_before(mut self, date: DateTime<Utc>) -> Self {
+        self.insert_date("modified__lte", date);
+        self
+    }
+
+    /// Filter txns executed at or after `date`
+    pub fn executed_after(mut self, date: DateTime<Utc>) -> Self {
+        self.insert_date("execution_date__gte", date);
+        self
+    }
+
+    /// Filter txns executed at or before `date`
+    pub fn executed_before(mut self, date: DateTime<Utc>) -> Self {
+        self.insert_date("execution_date__lte", date);
+        self
+    }
+
+    /// Filter txns submitted to the service at or after `date`
+    pub fn submitted_after(mut self, date: DateTime<Utc>) -> Self {
+        self.insert_date("submission_date__gte", date);
+        self
+    }
+
+    /// Filter txns submitted to the service at or before `date`
+    pub fn submitted_before(mut self, date: DateTime<Utc>) -> Self {
+        self.insert_date("submission_date__lte", date);
+        self
+    }
+
+    /// Filter by execution status. `true` for executed txns, `false` for
+    /// pending
     pub fn executed(mut self, executed: bool) -> Self {
-        self.insert("executed", executed.to_string());
+        self.insert("executed", executed);
         self
     }
 
-    /// Filter by trusted status
-    ///
-    /// TODO: what are the acceptable values here? Should this be an enum?
+    /// Filter by trusted status. Txns are trusted if proposed or confirmed
+    /// by an owner or delegate, or executed
     pub fn trusted(mut self, trusted: bool) -> Self {
-        self.insert("trusted", trusted.to_string());
+        self.insert("trusted", trusted);
+        self
+    }
+
+    /// Filter by failure status. `true` for executed txns that reverted
+    pub fn failed(mut self, failed: bool) -> Self {
+        self.insert("failed", failed);
+        self
+    }
+
+    /// Filter txns with or without any owner confirmations
+    pub fn has_confirmations(mut self, has_confirmations: bool) -> Self {
+        self.insert("has_confirmations", has_confirmations);
+        self
+    }
+
+    /// Filter by the origin recorded by the proposer
+    pub fn origin(mut self, origin: impl Into<String>) -> Self {
+        self.filters.insert("origin", origin.into());
         self
     }
 
@@ -304,10 +673,9 @@ impl<'a> MsigHistoryFilters<'a> {
     }
 
     /// Specify results ordering
-    ///
-    /// TODO: what are the acceptable values here? Should this be an enum?
-    pub fn ordering(mut self, ordering: &str) -> Self {
-        self.insert("ordering", ordering.to_owned());
+    pub fn ordering(mut self, ordering: MsigOrdering) -> Self {
+        self.filters
+            .insert("ordering", ordering.as_str().to_owned());
         self
     }
 
@@ -375,11 +743,82 @@ impl<'a> MsigHistoryFilters<'a> {
     }
 }
 
+/// Fixtures for tests of modules consuming multisig history
 #[cfg(test)]
-mod test {
+pub(crate) mod fixtures {
+    use ethers::types::{Address, H256};
 
     use super::MsigTxResponse;
 
+    /// A multisig transaction as returned by the API, confirmed by
+    /// `confirmed_by`
+    pub(crate) fn msig_tx_json(
+        safe: Address,
+        nonce: u64,
+        safe_tx_hash: H256,
+        executed: bool,
+        confirmed_by: &[Address],
+    ) -> serde_json::Value {
+        let confirmations: Vec<_> = confirmed_by
+            .iter()
+            .map(|owner| {
+                serde_json::json!({
+                    "owner": owner,
+                    "submissionDate": "2022-11-01T00:00:00Z",
+                    "transactionHash": null,
+                    "signature": "0x",
+                    "signatureType": "EOA",
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "safe": safe,
+            "to": Address::repeat_byte(1),
+            "value": "0",
+            "data": null,
+            "operation": 0,
+            "gasToken": Address::zero(),
+            "safeTxGas": 0,
+            "baseGas": 0,
+            "gasPrice": "0",
+            "refundReceiver": null,
+            "nonce": nonce,
+            "executionDate": null,
+            "submissionDate": "2022-11-01T00:00:00Z",
+            "modified": "2022-11-01T00:00:00Z",
+            "safeTxHash": safe_tx_hash,
+            "isExecuted": executed,
+            "isSuccessful": executed.then_some(true),
+            "confirmations": confirmations,
+            "trusted": true,
+            "signatures": null,
+        })
+    }
+
+    /// A multisig transaction. See [`msig_tx_json`]
+    pub(crate) fn msig_tx(
+        safe: Address,
+        nonce: u64,
+        safe_tx_hash: H256,
+        executed: bool,
+        confirmed_by: &[Address],
+    ) -> MsigTxResponse {
+        serde_json::from_value(msig_tx_json(
+            safe,
+            nonce,
+            safe_tx_hash,
+            executed,
+            confirmed_by,
+        ))
+        .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
     #[test]
     #[ignore] // TODO: move these integration test files to the repo
     fn it_parses() {
@@ -398,4 +837,270 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn it_decodes_multisend_parameters() {
+        let decoded: DecodedData = serde_json::from_value(serde_json::json!({
+            "method": "multiSend",
+            "parameters": [{
+                "name": "transactions",
+                "type": "bytes",
+                "value": "0x00a0b8",
+                "valueDecoded": [
+                    {
+                        "operation": 0,
+                        "to": "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+                        "value": "0",
+                        "data": "0xa9059cbb",
+                        "dataDecoded": {
+                            "method": "transfer",
+                            "parameters": [
+                                {"name": "to", "type": "address", "value": "0x425249Cf0F2f91f488E24cF7B1AA3186748f7516"},
+                                {"name": "value", "type": "uint256", "value": "25000000000000000000000000"}
+                            ]
+                        }
+                    },
+                    {
+                        "operation": 1,
+                        "to": "0x40A2aCCbd92BCA938b02010E17A5b8929b49130D",
+                        "value": "1000000000000000000",
+                        "data": null,
+                        "dataDecoded": null
+                    }
+                ]
+            }, {
+                "name": "owners",
+                "type": "address[]",
+                "value": ["0x425249Cf0F2f91f488E24cF7B1AA3186748f7516"]
+            }, {
+                "name": "swap",
+                "type": "(uint256,bool,string)",
+                "value": ["7", true, "hello"]
+            }, {
+                "name": "custom",
+                "type": "mystery",
+                "value": "???"
+            }]
+        }))
+        .unwrap();
+
+        let nested: Vec<_> = decoded.nested_transactions().collect();
+        assert_eq!(nested.len(), 2);
+        assert_eq!(nested[1].operation, Operations::DelegateCall);
+        assert_eq!(nested[1].value, U256::exp10(18));
+
+        let transfer = nested[0].data_decoded.as_ref().unwrap();
+        assert_eq!(
+            transfer.parameter("value").unwrap().value.as_uint(),
+            Some(U256::from(25) * U256::exp10(24))
+        );
+        assert!(transfer
+            .parameter("to")
+            .unwrap()
+            .value
+            .as_address()
+            .is_some());
+
+        assert!(matches!(
+            &decoded.parameter("owners").unwrap().value,
+            ParameterValue::Array(owners) if owners.len() == 1
+        ));
+        assert_eq!(
+            decoded.parameter("swap").unwrap().value,
+            ParameterValue::Tuple(vec![
+                ParameterValue::Uint(7.into()),
+                ParameterValue::Bool(true),
+                ParameterValue::String("hello".to_owned()),
+            ])
+        );
+        assert!(matches!(
+            decoded.parameter("custom").unwrap().value,
+            ParameterValue::Raw(_)
+        ));
+    }
+
+    #[test]
+    fn it_keeps_undecodable_parameters_raw() {
+        let decoded: DecodedData = serde_json::from_value(serde_json::json!({
+            "method": "setFlags",
+            "parameters": [{
+                "name": "enabled",
+                "type": "bool",
+                "value": "False"
+            }, {
+                "name": "paused",
+                "type": "bool",
+                "value": "maybe"
+            }, {
+                "name": "data",
+                "type": "bytes",
+                "value": "0x",
+                "valueDecoded": {"unexpected": "shape"}
+            }]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            decoded.parameter("enabled").unwrap().value,
+            ParameterValue::Bool(false)
+        );
+        assert_eq!(
+            decoded.parameter("paused").unwrap().value,
+            ParameterValue::Raw("maybe".into())
+        );
+        let data = decoded.parameter("data").unwrap();
+        assert!(data.value_decoded.is_none());
+        assert_eq!(
+            data.value_decoded_raw,
+            Some(serde_json::json!({"unexpected": "shape"}))
+        );
+    }
+
+    fn query(filters: MsigHistoryFilters<'_>) -> String {
+        filters
+            .to_url(Address::zero())
+            .query()
+            .unwrap_or_default()
+            .to_owned()
+    }
+
+    type Build = fn(MsigHistoryFilters<'_>) -> MsigHistoryFilters<'_>;
+
+    /// Dispatch each query to a mock service, checking the query actually
+    /// sent matches [`MsigHistoryFilters::to_url`]
+    async fn assert_dispatched(cases: &[Build]) {
+        use crate::rpc::mock::{page, MockService};
+
+        let service = MockService::start(|_, _| (200, page(vec![]))).await;
+        let client = service.client(1);
+        for (i, build) in cases.iter().enumerate() {
+            let expected = query(build(client.msig_history_builder()));
+            assert!(!expected.is_empty());
+            build(client.msig_history_builder())
+                .query(Address::zero())
+                .await
+                .unwrap();
+            let sent = &service.requests()[i];
+            assert_eq!(
+                sent.split_once('?').map(|(_, q)| q),
+                Some(expected.as_str())
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn it_dispatches_filters() {
+        assert_dispatched(&[
+            |f| f.nonce(5),
+            |f| f.min_nonce(3).max_nonce(9),
+            |f| f.min_value(10.into()),
+            |f| f.executor(Address::repeat_byte(0x42)),
+            |f| f.executed(true).failed(false).trusted(true),
+            |f| f.has_confirmations(true).origin("my app"),
+            |f| f.ordering(MsigOrdering::NonceDesc).limit(20).offset(40),
+        ])
+        .await;
+    }
+
+    #[tokio::test]
+    async fn it_dispatches_date_filters() {
+        fn date() -> DateTime<Utc> {
+            use chrono::TimeZone;
+            Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap()
+        }
+
+        assert_dispatched(&[
+            |f| f.modified_after(date()).modified_before(date()),
+            |f| f.executed_after(date()).executed_before(date()),
+            |f| f.submitted_after(date()).submitted_before(date()),
+            |f| f.ordering(MsigOrdering::Modified),
+        ])
+        .await;
+    }
+
+    #[test]
+    fn it_builds_query_strings() {
+        use chrono::TimeZone;
+
+        let client = SafeClient::ethereum();
+        let f = || client.msig_history_builder();
+        let addr: Address = "0x425249cf0f2f91f488e24cf7b1aa3186748f7516"
+            .parse()
+            .unwrap();
+        let hash = H256::repeat_byte(0xab);
+        let date = Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap();
+
+        assert_eq!(query(f()), "");
+        assert_eq!(query(f().min_nonce(3)), "nonce__gte=3");
+        assert_eq!(query(f().max_nonce(9)), "nonce__lte=9");
+        assert_eq!(query(f().min_nonce(3).max_nonce(9).nonce(5)), "nonce=5");
+        assert_eq!(query(f().nonce(5).min_nonce(3)), "nonce__gte=3");
+        assert_eq!(
+            query(f().safe_tx_hash(hash)),
+            format!("safe_tx_hash={hash:?}")
+        );
+        assert_eq!(
+            query(f().to(addr)),
+            "to=0x425249Cf0F2f91f488E24cF7B1AA3186748f7516"
+        );
+        assert_eq!(
+            query(f().executor(addr)),
+            "executor=0x425249Cf0F2f91f488E24cF7B1AA3186748f7516"
+        );
+        assert_eq!(query(f().min_value(10.into())), "value__gt=9");
+        assert_eq!(query(f().max_value(10.into())), "value__lt=11");
+        assert_eq!(
+            query(f().min_value(1.into()).max_value(10.into()).value(5.into())),
+            "value=5"
+        );
+        assert_eq!(
+            query(f().modified_after(date)),
+            "modified__gte=2023-01-01T00%3A00%3A00.000000Z"
+        );
+        assert_eq!(
+            query(f().modified_before(date)),
+            "modified__lte=2023-01-01T00%3A00%3A00.000000Z"
+        );
+        assert_eq!(
+            query(f().executed_after(date)),
+            "execution_date__gte=2023-01-01T00%3A00%3A00.000000Z"
+        );
+        assert_eq!(
+            query(f().executed_before(date)),
+            "execution_date__lte=2023-01-01T00%3A00%3A00.000000Z"
+        );
+        assert_eq!(
+            query(f().submitted_after(date)),
+            "submission_date__gte=2023-01-01T00%3A00%3A00.000000Z"
+        );
+        assert_eq!(
+            query(f().submitted_before(date)),
+            "submission_date__lte=2023-01-01T00%3A00%3A00.000000Z"
+        );
+        assert_eq!(query(f().executed(true)), "executed=true");
+        assert_eq!(query(f().trusted(false)), "trusted=false");
+        assert_eq!(query(f().failed(true)), "failed=true");
+        assert_eq!(query(f().has_confirmations(true)), "has_confirmations=true");
+        assert_eq!(query(f().origin("my app")), "origin=my+app");
+        assert_eq!(
+            query(f().transaction_hash(hash)),
+            format!("transaction_hash={hash:?}")
+        );
+        assert_eq!(
+            query(f().ordering(MsigOrdering::NonceDesc)),
+            "ordering=-nonce"
+        );
+        assert_eq!(
+            query(f().ordering(MsigOrdering::Modified)),
+            "ordering=modified"
+        );
+        assert_eq!(query(f().limit(20)), "limit=20");
+        assert_eq!(query(f().offset(40)), "offset=40");
+
+        // keys are sorted
+        assert_eq!(
+            query(f().nonce(5).executed(false).limit(1)),
+            "executed=false&limit=1&nonce=5"
+        );
+    }
 }
@@ -1,31 +1,39 @@
-use std::convert::Infallible;
+use std::{collections::BTreeMap, convert::Infallible, fmt};
 
 use ethers::{
     abi::{self, Tokenize},
+    contract::builders::ContractCall,
+    providers::Middleware,
     signers::Signer,
     types::{
-        transaction::eip712::{EIP712Domain, Eip712},
-        Address, Bytes, Signature, H256, U256,
+        transaction::{
+            eip2718::TypedTransaction,
+            eip712::{EIP712Domain, Eip712},
+        },
+        Address, Bytes, Eip1559TransactionRequest, NameOrAddress, Signature, TransactionRequest,
+        H256, U256,
     },
     utils::keccak256,
 };
 use reqwest::Url;
 
-use crate::rpc::common::{Operations, DOMAIN_SEPARATOR_TYPEHASH};
+use crate::rpc::common::{totp, Operations, DOMAIN_SEPARATOR_TYPEHASH};
 
 use super::{
-    common::{ChecksumAddress, SAFE_TX_TYPEHASH},
+    common::{ChecksumAddress, DELETE_REQUEST_TYPEHASH, SAFE_TX_TYPEHASH},
     estimate::EstimateRequest,
+    msig_history::MsigTxResponse,
 };
 
 /// Info about the metatransaction to be dispatched by the Safe
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct MetaTransactionData {
     /// The target of the tx
     pub to: ChecksumAddress,
     /// Native asset value to send to the target
-    pub value: u64,
+    #[serde(with = "crate::rpc::common::dec_u256_ser")]
+    pub value: U256,
     /// The data payload to send to the target
     #[serde(serialize_with = "crate::rpc::common::default_empty_bytes")]
     pub data: Option<Bytes>,
@@ -33,6 +41,111 @@ pub struct MetaTransactionData {
     pub operation: Option<Operations>,
 }
 
+/// Errors converting ethers transactions into [`MetaTransactionData`]
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum ConversionError {
+    /// The transaction does not specify a target
+    #[error("Transaction must specify to address")]
+    MissingTo,
+    /// The target is an ENS name. Use [`MetaTransactionData::resolve`] to
+    /// resolve it via a provider
+    #[error("Unresolved ENS name {0}")]
+    UnresolvedName(String),
+}
+
+/// Errors converting ethers transactions into [`MetaTransactionData`] while
+/// resolving ENS names
+#[derive(Debug, thiserror::Error)]
+pub enum ResolveError<M: Middleware> {
+    /// Conversion error
+    #[error("{0}")]
+    ConversionError(#[from] ConversionError),
+    /// Thrown when the middleware errors while resolving a name
+    #[error("{0}")]
+    MiddlewareError(M::Error),
+}
+
+impl MetaTransactionData {
+    /// Convert an ethers transaction, resolving an ENS name target via
+    /// `provider`. The operation is left unset, and defaults to Call
+    pub async fn resolve<M: Middleware>(
+        tx: &TypedTransaction,
+        provider: &M,
+    ) -> Result<Self, ResolveError<M>> {
+        if let Some(NameOrAddress::Name(ens_name)) = tx.to() {
+            let addr = provider
+                .resolve_name(ens_name)
+                .await
+                .map_err(ResolveError::MiddlewareError)?;
+            let mut tx = tx.clone();
+            tx.set_to(addr);
+            return Ok(Self::try_from(&tx)?);
+        }
+        Ok(Self::try_from(tx)?)
+    }
+}
+
+impl TryFrom<&TypedTransaction> for MetaTransactionData {
+    type Error = ConversionError;
+
+    fn try_from(tx: &TypedTransaction) -> Result<Self, Self::Error> {
+        let to = match tx.to().ok_or(ConversionError::MissingTo)? {
+            NameOrAddress::Name(name) => return Err(ConversionError::UnresolvedName(name.clone())),
+            NameOrAddress::Address(addr) => *addr,
+        };
+        Ok(Self {
+            to: to.into(),
+            value: tx.value().copied().unwrap_or_default(),
+            data: tx.data().cloned(),
+            operation: None,
+        })
+    }
+}
+
+impl TryFrom<TypedTransaction> for MetaTransactionData {
+    type Error = ConversionError;
+
+    fn try_from(tx: TypedTransaction) -> Result<Self, Self::Error> {
+        Self::try_from(&tx)
+    }
+}
+
+impl TryFrom<TransactionRequest> for MetaTransactionData {
+    type Error = ConversionError;
+
+    fn try_from(tx: TransactionRequest) -> Result<Self, Self::Error> {
+        let tx: TypedTransaction = tx.into();
+        Self::try_from(&tx)
+    }
+}
+
+impl TryFrom<Eip1559TransactionRequest> for MetaTransactionData {
+    type Error = ConversionError;
+
+    fn try_from(tx: Eip1559TransactionRequest) -> Result<Self, Self::Error> {
+        let tx: TypedTransaction = tx.into();
+        Self::try_from(&tx)
+    }
+}
+
+/// Propose abigen contract calls. Target, value and calldata are taken from
+/// the call
+impl<M, D> TryFrom<&ContractCall<M, D>> for MetaTransactionData {
+    type Error = ConversionError;
+
+    fn try_from(call: &ContractCall<M, D>) -> Result<Self, Self::Error> {
+        Self::try_from(&call.tx)
+    }
+}
+
+impl<M, D> TryFrom<ContractCall<M, D>> for MetaTransactionData {
+    type Error = ConversionError;
+
+    fn try_from(call: ContractCall<M, D>) -> Result<Self, Self::Error> {
+        Self::try_from(&call.tx)
+    }
+}
+
 impl<'a> From<&'a MetaTransactionData> for EstimateRequest<'a> {
     fn from(val: &'a MetaTransactionData) -> Self {
         EstimateRequest {
@@ -45,19 +158,22 @@ impl<'a> From<&'a MetaTransactionData> for EstimateRequest<'a> {
 }
 
 /// Configuration for the safe TX gas refunding system
-#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, Default)]
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, Default, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct SafeGasConfig {
     /// Gas to be forwarded to the callee. 0 for all available
-    pub safe_tx_gas: u64,
+    #[serde(with = "crate::rpc::common::dec_u256_ser")]
+    pub safe_tx_gas: U256,
     /// Gas cost that is independent of the internal transaction execution,
     /// (e.g. base transaction fee, signature check, payment of the refund)
-    pub base_gas: u64,
+    #[serde(with = "crate::rpc::common::dec_u256_ser")]
+    pub base_gas: U256,
     /// Maximum gas price that should be used for this transaction. 0 for no
     /// maximum. For base layer tokens, (e.g. ETH), this is adjusted to be no
     /// higher than that actual gas price used. For custom refund tokens, it
     /// may be any amount.
-    pub gas_price: u64,
+    #[serde(with = "crate::rpc::common::dec_u256_ser")]
+    pub gas_price: U256,
     /// Token address (or 0 if ETH) that is used for the reimbursement payment
     /// to the executor.
     pub gas_token: ChecksumAddress,
@@ -66,7 +182,7 @@ pub struct SafeGasConfig {
 }
 
 /// A Safe Transaction, suitable for EIP712 signing
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct SafeTransactionData {
     /// The internal transaction
@@ -79,6 +195,27 @@ pub struct SafeTransactionData {
     pub nonce: u64,
 }
 
+impl From<&MsigTxResponse> for SafeTransactionData {
+    fn from(tx: &MsigTxResponse) -> Self {
+        Self {
+            core: MetaTransactionData {
+                to: tx.to.into(),
+                value: tx.value,
+                data: tx.data.clone(),
+                operation: Some(tx.operation),
+            },
+            gas: SafeGasConfig {
+                safe_tx_gas: tx.safe_tx_gas.into(),
+                base_gas: tx.base_gas.into(),
+                gas_price: tx.gas_price,
+                gas_token: tx.gas_token.into(),
+                refund_receiver: tx.refund_receiver.into(),
+            },
+            nonce: tx.nonce,
+        }
+    }
+}
+
 impl<'a> From<&'a SafeTransactionData> for EstimateRequest<'a> {
     fn from(val: &'a SafeTransactionData) -> Self {
         From::from(&val.core)
@@ -156,6 +293,22 @@ impl Tokenize for &SafeTransactionData {
 }
 
 impl SafeTransactionData {
+    /// The canonical rejection of any proposal at `nonce`: a 0-value call from
+    /// the Safe to itself with no data and no gas refund. Once executed, it
+    /// consumes the nonce, cancelling all other proposals that use it
+    pub fn rejection(safe_address: Address, nonce: u64) -> Self {
+        Self {
+            core: MetaTransactionData {
+                to: safe_address.into(),
+                value: U256::zero(),
+                data: None,
+                operation: Some(Operations::Call),
+            },
+            gas: Default::default(),
+            nonce,
+        }
+    }
+
     pub(crate) fn eip712(&self, safe_address: Address, chain_id: u64) -> SafeEip712<'_> {
         SafeEip712 {
             safe_address,
@@ -175,8 +328,13 @@ impl SafeTransactionData {
             .into()
     }
 
+    /// The Safe's internal tx hash, produced by EIP712
+    pub fn safe_tx_hash(&self, safe_address: Address, chain_id: u64) -> H256 {
+        self.encode_eip712(safe_address, chain_id)
+    }
+
     /// Sign the safe transaction hash
-    async fn sign<S: Signer>(
+    pub(crate) async fn sign<S: Signer>(
         &self,
         signer: &S,
         safe_address: Address,
@@ -222,12 +380,89 @@ pub struct ProposeSignature {
     #[serde(with = "rsv_sig_ser")]
     /// Signature of the sender on the request. Must be in RSV format
     signature: Signature,
-    /// TODO: what is this?
+    /// Origin metadata, as a JSON string. See [`ProposalOrigin`]
     #[serde(default, skip_serializing_if = "Option::is_none")]
     origin: Option<String>,
 }
 
-mod rsv_sig_ser {
+/// Metadata describing who or what proposed a transaction. Stored by the
+/// service as a JSON string in the proposal's `origin`. The Safe UI displays
+/// the `name` & `url`, and other fields are preserved. The service may reject
+/// long origins, so keep notes short
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProposalOrigin {
+    /// Name of the app, bot or person proposing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// URL of the app proposing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// ID of the job that proposed, e.g. a CI run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<String>,
+    /// Free-form note for co-signers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// Other fields set by other proposers
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+impl ProposalOrigin {
+    /// Instantiate with a name
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: Some(name.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Set the URL
+    pub fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Set the job ID
+    pub fn with_job_id(mut self, job_id: impl Into<String>) -> Self {
+        self.job_id = Some(job_id.into());
+        self
+    }
+
+    /// Set the note
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+
+    /// Parse from a proposal's `origin`. `None` if the origin is not a JSON
+    /// object, e.g. a plain string set by another proposer
+    pub fn parse(origin: &str) -> Option<Self> {
+        serde_json::from_str(origin).ok()
+    }
+
+    /// Encode as the JSON string stored in a proposal's `origin`
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).expect("origin serializes to json")
+    }
+}
+
+impl fmt::Display for ProposalOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = vec![];
+        parts.extend(self.name.clone());
+        parts.extend(self.url.as_ref().map(|url| format!("<{url}>")));
+        parts.extend(self.job_id.as_ref().map(|job| format!("job {job}")));
+        parts.extend(self.note.as_ref().map(|note| format!("\"{note}\"")));
+        if parts.is_empty() {
+            return f.write_str("unknown");
+        }
+        f.write_str(&parts.join(" "))
+    }
+}
+
+pub(crate) mod rsv_sig_ser {
     use ethers::types::Signature;
     use serde::{Deserialize, Serialize};
 
@@ -258,6 +493,16 @@ impl ProposeSignature {
     pub fn sender(&self) -> ChecksumAddress {
         self.sender
     }
+
+    /// Getter for `origin`, as stored by the service
+    pub fn origin(&self) -> Option<&str> {
+        self.origin.as_deref()
+    }
+
+    /// The origin metadata, if the origin is set & parseable
+    pub fn proposal_origin(&self) -> Option<ProposalOrigin> {
+        self.origin.as_deref().and_then(ProposalOrigin::parse)
+    }
 }
 
 /// Propose a transaction for storage in the API pending signing by co-signers
@@ -301,4 +546,231 @@ impl ProposeRequest {
     pub fn signature(&self) -> &ProposeSignature {
         &self.signature
     }
+
+    /// Attach origin metadata. The origin is not signed, so may be set after
+    /// signing
+    pub fn with_origin(mut self, origin: &ProposalOrigin) -> Self {
+        self.signature.origin = Some(origin.encode());
+        self
+    }
+}
+
+/// A proposed rejection, and the queued proposals it replaces
+#[derive(Clone, Debug)]
+pub struct RejectionProposal {
+    /// The rejection transaction, as stored by the API
+    pub rejection: MsigTxResponse,
+    /// Other unexecuted proposals at the same nonce. These will be cancelled
+    /// when the rejection is executed
+    pub replaces: Vec<MsigTxResponse>,
+}
+
+/// Internal type to support 712 trait impl for proposal deletion
+#[derive(Clone, Debug)]
+struct DeleteRequestEip712 {
+    safe_address: Address,
+    chain_id: u64,
+    safe_tx_hash: H256,
+    totp: u64,
+}
+
+impl Eip712 for DeleteRequestEip712 {
+    type Error = Infallible;
+
+    fn domain(&self) -> Result<EIP712Domain, Self::Error> {
+        Ok(EIP712Domain {
+            name: Some("Safe Transaction Service".to_owned()),
+            version: Some("1.0".to_owned()),
+            chain_id: Some(self.chain_id.into()),
+            verifying_contract: Some(self.safe_address),
+            salt: None,
+        })
+    }
+
+    fn type_hash() -> Result<[u8; 32], Self::Error> {
+        Ok(From::from(*DELETE_REQUEST_TYPEHASH))
+    }
+
+    fn struct_hash(&self) -> Result<[u8; 32], Self::Error> {
+        let tokens = (
+            *DELETE_REQUEST_TYPEHASH,
+            self.safe_tx_hash,
+            U256::from(self.totp),
+        )
+            .into_tokens();
+        Ok(keccak256(abi::encode(&tokens)))
+    }
+}
+
+/// Delete a pending proposal from the API. Must be signed by the proposer
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteProposalRequest {
+    pub(crate) safe_tx_hash: H256,
+    #[serde(with = "rsv_sig_ser")]
+    pub(crate) signature: Signature,
+}
+
+impl DeleteProposalRequest {
+    /// Return the URL to which to dispatch this request
+    pub fn url(root: &Url, safe_tx_hash: H256) -> Url {
+        let path = format!("api/v1/multisig-transactions/{safe_tx_hash:?}/");
+        let mut url = root.clone();
+        url.set_path(&path);
+        url
+    }
+
+    /// Sign the deletion authorization for `safe_tx_hash` with the proposer's
+    /// signer
+    pub async fn sign<S: Signer>(
+        signer: &S,
+        safe_address: Address,
+        safe_tx_hash: H256,
+        chain_id: u64,
+    ) -> Result<Self, S::Error> {
+        let eip712 = DeleteRequestEip712 {
+            safe_address,
+            chain_id,
+            safe_tx_hash,
+            totp: totp(),
+        };
+        let signature = signer.sign_typed_data(&eip712).await?;
+        Ok(Self {
+            safe_tx_hash,
+            signature,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_attaches_origins() {
+        // as set by the Safe UI
+        let origin =
+            ProposalOrigin::parse(r#"{"url":"https://app.uniswap.org","name":"Uniswap"}"#).unwrap();
+        assert_eq!(origin.name.as_deref(), Some("Uniswap"));
+        assert_eq!(origin.to_string(), "Uniswap <https://app.uniswap.org>");
+        assert!(ProposalOrigin::parse("some string").is_none());
+
+        let origin = ProposalOrigin::new("treasury-bot")
+            .with_job_id("1234")
+            .with_note("monthly payroll");
+        assert_eq!(
+            origin.encode(),
+            r#"{"name":"treasury-bot","jobId":"1234","note":"monthly payroll"}"#
+        );
+
+        let signer: ethers::signers::LocalWallet =
+            "1c3a7cdd2270579847aaec11680312cbf4d3c36886232b413ab6529593228ec2"
+                .parse()
+                .unwrap();
+        let safe = Address::repeat_byte(0x5a);
+        let request = SafeTransactionData::rejection(safe, 0)
+            .into_request(&signer, safe, 1)
+            .await
+            .unwrap();
+        assert!(serde_json::to_value(&request)
+            .unwrap()
+            .get("origin")
+            .is_none());
+
+        let request = request.with_origin(&origin);
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["origin"], origin.encode());
+        let request: ProposeRequest = serde_json::from_value(json).unwrap();
+        assert_eq!(request.signature().proposal_origin(), Some(origin));
+    }
+
+    #[test]
+    fn it_serializes_large_values_as_decimal() {
+        // 1 million ether, far above u64::MAX wei
+        let value = U256::exp10(24);
+        let mut tx = SafeTransactionData::rejection(Address::repeat_byte(0x11), 3);
+        tx.core.value = value;
+        tx.gas.gas_price = U256::exp10(20);
+
+        let json = serde_json::to_value(&tx).unwrap();
+        assert_eq!(json["value"], "1000000000000000000000000");
+        assert_eq!(json["gasPrice"], "100000000000000000000");
+        assert_eq!(json["safeTxGas"], "0");
+
+        let parsed: SafeTransactionData = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.core.value, value);
+        assert_eq!(
+            parsed.safe_tx_hash(Address::repeat_byte(0x11), 1),
+            tx.safe_tx_hash(Address::repeat_byte(0x11), 1)
+        );
+    }
+
+    #[test]
+    fn it_converts_ethers_txns() {
+        let to = Address::repeat_byte(0x22);
+        let req = TransactionRequest::new()
+            .to(to)
+            .value(U256::exp10(21))
+            .data(vec![0xde, 0xad]);
+        let meta = MetaTransactionData::try_from(req).unwrap();
+        assert_eq!(Address::from(meta.to), to);
+        assert_eq!(meta.value, U256::exp10(21));
+        assert_eq!(meta.data.unwrap().to_vec(), vec![0xde, 0xad]);
+        assert!(meta.operation.is_none());
+
+        let ens = Eip1559TransactionRequest::new().to("safe.eth");
+        assert!(matches!(
+            MetaTransactionData::try_from(ens),
+            Err(ConversionError::UnresolvedName(_))
+        ));
+        assert!(matches!(
+            MetaTransactionData::try_from(TransactionRequest::new()),
+            Err(ConversionError::MissingTo)
+        ));
+    }
+
+    #[test]
+    fn it_hashes_delete_requests() {
+        let safe_address: Address = "0x38CD8Fa77ECEB4b1edB856Ed27aac6A6c6Dc88ca"
+            .parse()
+            .unwrap();
+        let safe_tx_hash = H256::repeat_byte(0xab);
+        let request = DeleteRequestEip712 {
+            safe_address,
+            chain_id: 5,
+            safe_tx_hash,
+            totp: 466_000,
+        };
+
+        // as computed by the service
+        let domain_separator = keccak256(abi::encode(
+            &(
+                H256::from(keccak256(
+                    "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)",
+                )),
+                H256::from(keccak256("Safe Transaction Service")),
+                H256::from(keccak256("1.0")),
+                U256::from(5),
+                safe_address,
+            )
+                .into_tokens(),
+        ));
+        let struct_hash = keccak256(abi::encode(
+            &(
+                H256::from(keccak256("DeleteRequest(bytes32 safeTxHash,uint256 totp)")),
+                safe_tx_hash,
+                U256::from(466_000),
+            )
+                .into_tokens(),
+        ));
+        let digest = keccak256([&[0x19, 0x01], &domain_separator[..], &struct_hash[..]].concat());
+
+        assert_eq!(request.encode_eip712().unwrap(), digest);
+        assert_eq!(
+            H256::from(digest),
+            "0x1cd2170564792aaeef063d0fe259ee2cec8c5b469282535a720d13afbda3fb79"
+                .parse()
+                .unwrap()
+        );
+    }
 }
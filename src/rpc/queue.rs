@@ -0,0 +1,198 @@
+use std::collections::BTreeMap;
+
+use ethers::types::Address;
+
+use super::{info::SafeInfoResponse, msig_history::MsigTxResponse};
+
+/// An unexecuted multisig transaction, with its signing progress
+#[derive(Clone, Debug)]
+pub struct PendingTx {
+    /// The transaction, as stored by the API
+    pub tx: MsigTxResponse,
+    /// Confirmations required to execute. Falls back to the Safe's current
+    /// threshold if the API does not specify
+    pub confirmations_required: u32,
+    /// Number of owner confirmations still needed to reach
+    /// `confirmations_required`
+    pub missing_confirmations: u32,
+    /// Current owners that have not yet confirmed the transaction
+    pub missing_signers: Vec<Address>,
+}
+
+impl PendingTx {
+    fn new(tx: MsigTxResponse, info: &SafeInfoResponse) -> Self {
+        let confirmations_required = tx.confirmations_required.unwrap_or(info.threshold);
+
+        // confirmations by removed owners do not count toward the threshold
+        let confirmed = tx
+            .confirmations
+            .iter()
+            .filter(|c| info.owners.contains(&c.owner))
+            .count() as u32;
+
+        let missing_signers = info
+            .owners
+            .iter()
+            .filter(|owner| !tx.confirmations.iter().any(|c| c.owner == **owner))
+            .copied()
+            .collect();
+
+        Self {
+            tx,
+            confirmations_required,
+            missing_confirmations: confirmations_required.saturating_sub(confirmed),
+            missing_signers,
+        }
+    }
+
+    /// True if the transaction has enough confirmations to be executed
+    pub fn is_executable(&self) -> bool {
+        self.missing_confirmations == 0
+    }
+}
+
+/// All pending transactions sharing a nonce
+#[derive(Clone, Debug)]
+pub struct QueuedNonce {
+    /// The Safe nonce
+    pub nonce: u64,
+    /// The pending transactions using this nonce
+    pub transactions: Vec<PendingTx>,
+}
+
+impl QueuedNonce {
+    /// True if more than one transaction was proposed for this nonce. At most
+    /// one of them can be executed
+    pub fn is_conflicted(&self) -> bool {
+        self.transactions.len() > 1
+    }
+
+    /// True if any transaction at this nonce is a rejection
+    pub fn has_rejection(&self) -> bool {
+        self.transactions.iter().any(|t| t.tx.is_rejection())
+    }
+}
+
+/// The queue of a Safe: unexecuted transactions with nonces at or above the
+/// on-chain nonce, grouped by nonce in ascending order
+#[derive(Clone, Debug)]
+pub struct PendingQueue {
+    /// The Safe's address
+    pub safe_address: Address,
+    /// The current on-chain nonce
+    pub nonce: u64,
+    /// The current threshold
+    pub threshold: u32,
+    /// The current owners
+    pub owners: Vec<Address>,
+    /// Pending transactions, grouped by nonce
+    pub nonces: Vec<QueuedNonce>,
+}
+
+impl PendingQueue {
+    /// Build the queue from the Safe's info and its multisig transactions.
+    /// Executed transactions and those with stale nonces are discarded
+    pub fn new(info: &SafeInfoResponse, txns: impl IntoIterator<Item = MsigTxResponse>) -> Self {
+        let mut grouped: BTreeMap<u64, Vec<PendingTx>> = BTreeMap::new();
+        txns.into_iter()
+            .filter(|tx| !tx.is_executed && tx.nonce >= info.nonce)
+            .for_each(|tx| {
+                grouped
+                    .entry(tx.nonce)
+                    .or_default()
+                    .push(PendingTx::new(tx, info))
+            });
+
+        Self {
+            safe_address: info.safe_address,
+            nonce: info.nonce,
+            threshold: info.threshold,
+            owners: info.owners.clone(),
+            nonces: grouped
+                .into_iter()
+                .map(|(nonce, transactions)| QueuedNonce {
+                    nonce,
+                    transactions,
+                })
+                .collect(),
+        }
+    }
+
+    /// Iterate over nonces with more than one pending transaction
+    pub fn conflicts(&self) -> impl Iterator<Item = &QueuedNonce> {
+        self.nonces.iter().filter(|n| n.is_conflicted())
+    }
+
+    /// The pending transactions at `nonce`, if any
+    pub fn at_nonce(&self, nonce: u64) -> Option<&QueuedNonce> {
+        self.nonces.iter().find(|n| n.nonce == nonce)
+    }
+
+    /// True if there are no pending transactions
+    pub fn is_empty(&self) -> bool {
+        self.nonces.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ethers::types::H256;
+
+    use super::*;
+    use crate::rpc::msig_history::fixtures::msig_tx;
+
+    const OWNER_A: &str = "0xD5F586B9b2abbbb9a9ffF936690A54F9849dbC97";
+    const OWNER_B: &str = "0x425249Cf0F2f91f488E24cF7B1AA3186748f7516";
+    const SAFE: &str = "0x38CD8Fa77ECEB4b1edB856Ed27aac6A6c6Dc88ca";
+
+    fn tx(nonce: u64, hash_byte: u8, executed: bool, confirmed_by: &[&str]) -> MsigTxResponse {
+        let confirmed_by: Vec<Address> = confirmed_by.iter().map(|o| o.parse().unwrap()).collect();
+        msig_tx(
+            SAFE.parse().unwrap(),
+            nonce,
+            H256::repeat_byte(hash_byte),
+            executed,
+            &confirmed_by,
+        )
+    }
+
+    #[test]
+    fn it_groups_by_nonce() {
+        let info = SafeInfoResponse {
+            safe_address: SAFE.parse().unwrap(),
+            nonce: 5,
+            threshold: 2,
+            owners: vec![OWNER_A.parse().unwrap(), OWNER_B.parse().unwrap()],
+            master_copy: Default::default(),
+            modules: vec![],
+            fallback_handler: Default::default(),
+            guard: Default::default(),
+            version: None,
+        };
+        let queue = PendingQueue::new(
+            &info,
+            vec![
+                tx(4, 1, true, &[OWNER_A, OWNER_B]),
+                tx(6, 2, false, &[OWNER_A, OWNER_B]),
+                tx(5, 3, false, &[OWNER_A]),
+                tx(5, 4, false, &[]),
+            ],
+        );
+
+        assert_eq!(queue.nonces.len(), 2);
+        assert_eq!(queue.conflicts().count(), 1);
+
+        let five = queue.at_nonce(5).unwrap();
+        assert!(five.is_conflicted());
+        assert_eq!(five.transactions[0].missing_confirmations, 1);
+        assert_eq!(
+            five.transactions[0].missing_signers,
+            vec![OWNER_B.parse::<Address>().unwrap()]
+        );
+        assert_eq!(five.transactions[1].missing_confirmations, 2);
+
+        let six = queue.at_nonce(6).unwrap();
+        assert!(!six.is_conflicted());
+        assert!(six.transactions[0].is_executable());
+    }
+}
@@ -0,0 +1,277 @@
+use std::fmt;
+
+use ethers::types::{Address, Bytes, U256};
+
+use crate::{
+    contracts::{
+        is_multisend, unpack_multisend, MULTISEND_CONTRACT, MULTISEND_SELECTOR, SAFE_CONTRACT,
+    },
+    rpc::{
+        common::Operations,
+        propose::{MetaTransactionData, SafeTransactionData},
+    },
+};
+
+/// Safe functions that change owners, threshold, modules, guard, fallback
+/// handler or singleton
+pub const SAFE_CONFIG_FUNCTIONS: &[&str] = &[
+    "addOwnerWithThreshold",
+    "removeOwner",
+    "swapOwner",
+    "changeThreshold",
+    "enableModule",
+    "disableModule",
+    "setGuard",
+    "setFallbackHandler",
+    "changeMasterCopy",
+];
+
+/// A dangerous pattern detected in a transaction about to be signed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Hazard {
+    /// Delegatecall to a contract other than a canonical MultiSend. The
+    /// target runs with full control of the Safe's storage, and may brick it
+    UnknownDelegateCall {
+        /// The target
+        to: Address,
+    },
+    /// The executor is refunded in a token other than the native asset, or
+    /// refunds are paid to a third party
+    GasRefund {
+        /// Refund gas price
+        gas_price: U256,
+        /// Refund token
+        gas_token: Address,
+        /// Refund receiver
+        refund_receiver: Address,
+    },
+    /// The Safe calls itself to change its configuration
+    SafeConfigChange {
+        /// The called Safe function
+        method: &'static str,
+    },
+}
+
+impl fmt::Display for Hazard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Hazard::UnknownDelegateCall { to } => {
+                write!(f, "delegatecall to unknown contract {to:?}")
+            }
+            Hazard::GasRefund {
+                gas_price,
+                gas_token,
+                refund_receiver,
+            } => write!(
+                f,
+                "gas refund at price {gas_price} in token {gas_token:?} to {refund_receiver:?}"
+            ),
+            Hazard::SafeConfigChange { method } => write!(f, "Safe configuration change: {method}"),
+        }
+    }
+}
+
+/// Hazards explicitly allowed by the signer. Transactions with any other
+/// hazard are refused before signing. Nothing is allowed by default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AllowedHazards {
+    /// Allow delegatecalls to contracts other than canonical MultiSend
+    pub delegatecall: bool,
+    /// Allow refunds in tokens, or to third parties
+    pub gas_refund: bool,
+    /// Allow changes to owners, threshold, modules, guard, fallback handler
+    /// or singleton
+    pub safe_config_change: bool,
+}
+
+impl AllowedHazards {
+    /// Allow every hazard
+    pub fn all() -> Self {
+        Self {
+            delegatecall: true,
+            gas_refund: true,
+            safe_config_change: true,
+        }
+    }
+
+    /// True if `hazard` is allowed
+    pub fn allows(&self, hazard: &Hazard) -> bool {
+        match hazard {
+            Hazard::UnknownDelegateCall { .. } => self.delegatecall,
+            Hazard::GasRefund { .. } => self.gas_refund,
+            Hazard::SafeConfigChange { .. } => self.safe_config_change,
+        }
+    }
+}
+
+/// Transaction refused before signing, due to hazards not explicitly allowed
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Refusing to sign unsafe transaction: {}", .hazards.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+pub struct UnsafeTransaction {
+    /// The disallowed hazards
+    pub hazards: Vec<Hazard>,
+}
+
+/// Detect hazards in a transaction of the Safe at `safe_address`. Calls in
+/// canonical MultiSend batches are inspected individually. Native refunds to
+/// the executor (zero `refund_receiver`) or to `signer` are not hazardous
+pub fn detect_hazards(
+    safe_address: Address,
+    signer: Address,
+    tx: &SafeTransactionData,
+) -> Vec<Hazard> {
+    let mut hazards = vec![];
+
+    let gas_token = Address::from(tx.gas.gas_token);
+    let refund_receiver = Address::from(tx.gas.refund_receiver);
+    if !tx.gas.gas_price.is_zero()
+        && (!gas_token.is_zero() || !(refund_receiver.is_zero() || refund_receiver == signer))
+    {
+        hazards.push(Hazard::GasRefund {
+            gas_price: tx.gas.gas_price,
+            gas_token,
+            refund_receiver,
+        });
+    }
+
+    detect_call_hazards(safe_address, &tx.core, &mut hazards);
+    hazards
+}
+
+fn detect_call_hazards(safe_address: Address, call: &MetaTransactionData, out: &mut Vec<Hazard>) {
+    let to = Address::from(call.to);
+    let data = call.data.as_deref().unwrap_or_default();
+
+    if call.operation == Some(Operations::DelegateCall) {
+        let nested = (is_multisend(to) && data.starts_with(&MULTISEND_SELECTOR))
+            .then(|| {
+                MULTISEND_CONTRACT
+                    .decode::<Bytes, _>("multiSend", data)
+                    .ok()
+                    .and_then(|packed| unpack_multisend(&packed))
+            })
+            .flatten();
+        match nested {
+            Some(nested) => nested
+                .iter()
+                .for_each(|call| detect_call_hazards(safe_address, call, out)),
+            None => out.push(Hazard::UnknownDelegateCall { to }),
+        }
+        return;
+    }
+
+    if to == safe_address && data.len() >= 4 {
+        let method = SAFE_CONFIG_FUNCTIONS.iter().find(|name| {
+            SAFE_CONTRACT
+                .abi()
+                .function(name)
+                .is_ok_and(|f| f.short_signature() == data[..4])
+        });
+        if let Some(method) = method {
+            out.push(Hazard::SafeConfigChange { method });
+        }
+    }
+}
+
+/// Detect hazards, and error if any is not allowed
+pub fn check_hazards(
+    safe_address: Address,
+    signer: Address,
+    tx: &SafeTransactionData,
+    allowed: &AllowedHazards,
+) -> Result<(), UnsafeTransaction> {
+    let hazards: Vec<_> = detect_hazards(safe_address, signer, tx)
+        .into_iter()
+        .filter(|h| !allowed.allows(h))
+        .collect();
+    if hazards.is_empty() {
+        Ok(())
+    } else {
+        Err(UnsafeTransaction { hazards })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        contracts::{multisend_calldata, MULTISEND_ADDRESSES},
+        tx::native_transfer,
+    };
+
+    #[test]
+    fn it_detects_hazards_in_batches() {
+        let safe = Address::repeat_byte(0x5a);
+        let signer = Address::repeat_byte(0x01);
+        let add_owner = MetaTransactionData {
+            to: safe.into(),
+            value: U256::zero(),
+            data: Some(
+                SAFE_CONTRACT
+                    .encode(
+                        "addOwnerWithThreshold",
+                        (Address::repeat_byte(0x66), U256::one()),
+                    )
+                    .unwrap(),
+            ),
+            operation: Some(Operations::Call),
+        };
+        let unknown_delegatecall = MetaTransactionData {
+            to: Address::repeat_byte(0x77).into(),
+            value: U256::zero(),
+            data: Some(vec![0xde, 0xad, 0xbe, 0xef].into()),
+            operation: Some(Operations::DelegateCall),
+        };
+        let mut tx = SafeTransactionData {
+            core: MetaTransactionData {
+                to: MULTISEND_ADDRESSES[0].into(),
+                value: U256::zero(),
+                data: Some(multisend_calldata(&[
+                    native_transfer(signer, U256::one()),
+                    add_owner,
+                    unknown_delegatecall,
+                ])),
+                operation: Some(Operations::DelegateCall),
+            },
+            gas: Default::default(),
+            nonce: 0,
+        };
+        tx.gas.gas_price = 1.into();
+        tx.gas.refund_receiver = signer.into();
+
+        assert_eq!(
+            detect_hazards(safe, signer, &tx),
+            vec![
+                Hazard::SafeConfigChange {
+                    method: "addOwnerWithThreshold"
+                },
+                Hazard::UnknownDelegateCall {
+                    to: Address::repeat_byte(0x77)
+                },
+            ]
+        );
+
+        let allowed = AllowedHazards {
+            safe_config_change: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            check_hazards(safe, signer, &tx, &allowed)
+                .unwrap_err()
+                .hazards
+                .len(),
+            1
+        );
+        check_hazards(safe, signer, &tx, &AllowedHazards::all()).unwrap();
+
+        // refunds to third parties are hazardous
+        tx.gas.refund_receiver = Address::repeat_byte(0x99).into();
+        assert!(matches!(
+            detect_hazards(safe, signer, &tx)[0],
+            Hazard::GasRefund { .. }
+        ));
+
+        // rejections are not
+        assert!(detect_hazards(safe, signer, &SafeTransactionData::rejection(safe, 0)).is_empty());
+    }
+}
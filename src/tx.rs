@@ -1 +1,347 @@
+use ethers::{
+    types::{Address, Bytes, U256},
+    utils::format_units,
+};
 
+use crate::{
+    client::ClientResult,
+    contracts::{ERC1155_CONTRACT, ERC20_CONTRACT, ERC721_CONTRACT},
+    rpc::{
+        balances::{BalanceResponse, BalancesResponse},
+        common::Operations,
+        propose::MetaTransactionData,
+        tokens::TokenResponse,
+    },
+    ClientError, SafeClient,
+};
+
+/// Decimals of the native asset
+pub const NATIVE_DECIMALS: u32 = 18;
+
+/// Transfer builder errors
+#[derive(Debug, thiserror::Error)]
+pub enum TransferError {
+    /// Client error
+    #[error("{0}")]
+    ClientError(#[from] ClientError),
+    /// The amount could not be parsed
+    #[error("Invalid amount {0:?}")]
+    InvalidAmount(String),
+    /// The amount has more fractional digits than the token's decimals
+    #[error("Amount {amount:?} has more than {decimals} decimals")]
+    TooManyDecimals {
+        /// The amount
+        amount: String,
+        /// The token's decimals
+        decimals: u32,
+    },
+    /// The token's decimals are not known
+    #[error("Unknown decimals for token {0:?}")]
+    UnknownDecimals(Address),
+    /// The Safe holds none of the token
+    #[error("Safe holds no balance of token {0:?}")]
+    NoBalance(Address),
+    /// The Safe's balance is lower than the transfer amount
+    #[error("Insufficient balance of {token:?}: have {balance}, need {amount}")]
+    InsufficientBalance {
+        /// The token, `None` for the native asset
+        token: Option<Address>,
+        /// The Safe's balance
+        balance: U256,
+        /// The transfer amount
+        amount: U256,
+    },
+}
+
+/// Parse a human-readable amount (e.g. `"1.5"`) into base units, given the
+/// token's decimals. Errors rather than truncating amounts with more
+/// fractional digits than the token's decimals, or overflowing
+pub fn parse_amount(amount: &str, decimals: u32) -> Result<U256, TransferError> {
+    let invalid = || TransferError::InvalidAmount(amount.to_owned());
+
+    let (whole, fraction) = amount.trim().split_once('.').unwrap_or((amount.trim(), ""));
+    let is_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if whole.is_empty() && fraction.is_empty() || !is_digits(whole) || !is_digits(fraction) {
+        return Err(invalid());
+    }
+
+    // trailing zeros never truncate
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.len() > decimals as usize {
+        return Err(TransferError::TooManyDecimals {
+            amount: amount.to_owned(),
+            decimals,
+        });
+    }
+
+    let scale = U256::from(10)
+        .checked_pow(decimals.into())
+        .ok_or_else(invalid)?;
+    let whole = match whole {
+        "" => U256::zero(),
+        whole => U256::from_dec_str(whole).map_err(|_| invalid())?,
+    };
+    let fraction = match fraction {
+        "" => U256::zero(),
+        fraction => {
+            U256::from_dec_str(fraction).map_err(|_| invalid())?
+                * U256::exp10(decimals as usize - fraction.len())
+        }
+    };
+    whole
+        .checked_mul(scale)
+        .and_then(|whole| whole.checked_add(fraction))
+        .ok_or_else(invalid)
+}
+
+/// Format an amount in base units as human-readable, given the token's
+/// decimals
+pub fn format_amount(amount: U256, decimals: u32) -> String {
+    format_units(amount, decimals).unwrap_or_else(|_| amount.to_string())
+}
+
+impl TokenResponse {
+    /// Parse a human-readable amount of this token into base units
+    pub fn parse_amount(&self, amount: &str) -> Result<U256, TransferError> {
+        let decimals = self.decimals.ok_or_else(|| {
+            TransferError::UnknownDecimals(self.address.parse().unwrap_or_default())
+        })?;
+        parse_amount(amount, decimals)
+    }
+}
+
+impl BalanceResponse {
+    /// The token's decimals. Native balances have 18 decimals
+    pub fn decimals(&self) -> Option<u32> {
+        match &self.token {
+            Some(info) => info.decimals,
+            None => Some(NATIVE_DECIMALS),
+        }
+    }
+
+    /// Parse a human-readable amount of this balance's token into base units
+    pub fn parse_amount(&self, amount: &str) -> Result<U256, TransferError> {
+        let decimals = self.decimals().ok_or_else(|| {
+            TransferError::UnknownDecimals(self.token_address.unwrap_or_default())
+        })?;
+        parse_amount(amount, decimals)
+    }
+
+    /// Error if the balance is lower than `amount`
+    pub fn ensure_covers(&self, amount: U256) -> Result<(), TransferError> {
+        if self.balance < amount {
+            return Err(TransferError::InsufficientBalance {
+                token: self.token_address,
+                balance: self.balance,
+                amount,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// A native asset transfer
+pub fn native_transfer(to: Address, amount: U256) -> MetaTransactionData {
+    MetaTransactionData {
+        to: to.into(),
+        value: amount,
+        data: None,
+        operation: Some(Operations::Call),
+    }
+}
+
+/// An ERC20 `transfer` of `amount` base units of `token`
+pub fn erc20_transfer(token: Address, to: Address, amount: U256) -> MetaTransactionData {
+    token_call(
+        token,
+        ERC20_CONTRACT
+            .encode("transfer", (to, amount))
+            .expect("valid abi encoding"),
+    )
+}
+
+/// An ERC721 `safeTransferFrom` of `token_id` from the Safe at `from`
+pub fn erc721_transfer(
+    token: Address,
+    from: Address,
+    to: Address,
+    token_id: U256,
+) -> MetaTransactionData {
+    token_call(
+        token,
+        ERC721_CONTRACT
+            .encode("safeTransferFrom", (from, to, token_id))
+            .expect("valid abi encoding"),
+    )
+}
+
+/// An ERC1155 `safeTransferFrom` of `amount` of token `id` from the Safe at
+/// `from`
+pub fn erc1155_transfer(
+    token: Address,
+    from: Address,
+    to: Address,
+    id: U256,
+    amount: U256,
+    data: Bytes,
+) -> MetaTransactionData {
+    token_call(
+        token,
+        ERC1155_CONTRACT
+            .encode("safeTransferFrom", (from, to, id, amount, data))
+            .expect("valid abi encoding"),
+    )
+}
+
+fn token_call(token: Address, data: Bytes) -> MetaTransactionData {
+    MetaTransactionData {
+        to: token.into(),
+        value: U256::zero(),
+        data: Some(data),
+        operation: Some(Operations::Call),
+    }
+}
+
+/// Builds transfers from a Safe, taking human-readable amounts. Fungible
+/// transfers are checked against the Safe's balances from the API
+#[derive(Debug, Clone)]
+pub struct TransferBuilder<'a> {
+    pub(crate) client: &'a SafeClient,
+    pub(crate) safe_address: Address,
+    pub(crate) balances: Option<BalancesResponse>,
+}
+
+impl<'a> TransferBuilder<'a> {
+    /// Instantiate from a client
+    pub(crate) fn new(client: &'a SafeClient, safe_address: Address) -> Self {
+        Self {
+            client,
+            safe_address,
+            balances: None,
+        }
+    }
+
+    /// The Safe's balance of `token`, `None` for the native asset. Balances
+    /// are fetched once and reused for subsequent transfers
+    pub async fn balance(
+        &mut self,
+        token: Option<Address>,
+    ) -> ClientResult<Option<&BalanceResponse>> {
+        if self.balances.is_none() {
+            self.balances = Some(self.client.balances(self.safe_address).await?);
+        }
+        Ok(self
+            .balances
+            .iter()
+            .flatten()
+            .find(|b| b.token_address == token))
+    }
+
+    /// Send `amount` (e.g. `"1.5"`) of the native asset
+    pub async fn native(
+        &mut self,
+        to: Address,
+        amount: &str,
+    ) -> Result<MetaTransactionData, TransferError> {
+        let amount = parse_amount(amount, NATIVE_DECIMALS)?;
+        match self.balance(None).await? {
+            Some(balance) => balance.ensure_covers(amount)?,
+            None if !amount.is_zero() => {
+                return Err(TransferError::InsufficientBalance {
+                    token: None,
+                    balance: U256::zero(),
+                    amount,
+                })
+            }
+            None => {}
+        }
+        Ok(native_transfer(to, amount))
+    }
+
+    /// Send `amount` (e.g. `"100.25"`) of an ERC20 token, using the token's
+    /// decimals from the API
+    pub async fn erc20(
+        &mut self,
+        token: Address,
+        to: Address,
+        amount: &str,
+    ) -> Result<MetaTransactionData, TransferError> {
+        let balance = self
+            .balance(Some(token))
+            .await?
+            .ok_or(TransferError::NoBalance(token))?;
+        let amount = balance.parse_amount(amount)?;
+        balance.ensure_covers(amount)?;
+        Ok(erc20_transfer(token, to, amount))
+    }
+
+    /// Send an ERC721 token. Ownership is not checked
+    pub fn erc721(&self, token: Address, to: Address, token_id: U256) -> MetaTransactionData {
+        erc721_transfer(token, self.safe_address, to, token_id)
+    }
+
+    /// Send `amount` of an ERC1155 token. Balances are not checked
+    pub fn erc1155(
+        &self,
+        token: Address,
+        to: Address,
+        id: U256,
+        amount: U256,
+    ) -> MetaTransactionData {
+        erc1155_transfer(token, self.safe_address, to, id, amount, Bytes::default())
+    }
+}
+
+impl SafeClient {
+    /// Create a transfer builder for the Safe at `safe_address`
+    pub fn transfers(&self, safe_address: Address) -> TransferBuilder<'_> {
+        TransferBuilder::new(self, safe_address)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_parses_amounts() {
+        assert_eq!(parse_amount("1.5", 6).unwrap(), U256::from(1_500_000));
+        assert_eq!(parse_amount("2", 18).unwrap(), U256::exp10(18) * 2);
+        assert!(parse_amount("-1", 18).is_err());
+        assert!(parse_amount("abc", 18).is_err());
+        assert!(parse_amount(".", 18).is_err());
+        assert_eq!(parse_amount(".5", 1).unwrap(), U256::from(5));
+        assert_eq!(parse_amount("1.500", 1).unwrap(), U256::from(15));
+        // extra fractional digits are refused, not truncated
+        assert!(matches!(
+            parse_amount("1.0000005", 6),
+            Err(TransferError::TooManyDecimals { decimals: 6, .. })
+        ));
+        // overflow errors rather than panicking
+        let long = "9".repeat(75);
+        assert!(matches!(
+            parse_amount(&long, 18),
+            Err(TransferError::InvalidAmount(_))
+        ));
+        assert!(matches!(
+            parse_amount(&format!("{}.5", "9".repeat(60)), 18),
+            Err(TransferError::InvalidAmount(_))
+        ));
+        assert_eq!(format_amount(U256::from(1_500_000), 6), "1.500000");
+    }
+
+    #[test]
+    fn it_encodes_transfers() {
+        let tx = erc20_transfer(Address::repeat_byte(1), Address::repeat_byte(2), 5.into());
+        // transfer(address,uint256)
+        assert_eq!(tx.data.unwrap()[..4], [0xa9, 0x05, 0x9c, 0xbb]);
+        let tx = erc721_transfer(
+            Address::repeat_byte(1),
+            Address::repeat_byte(2),
+            Address::repeat_byte(3),
+            5.into(),
+        );
+        // safeTransferFrom(address,address,uint256)
+        assert_eq!(tx.data.unwrap()[..4], [0x42, 0x84, 0x2e, 0x0e]);
+    }
+}
@@ -0,0 +1,475 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use async_stream::stream;
+use ethers::types::{Address, H256};
+use tokio::time::Instant;
+use tokio_stream::StreamExt;
+
+use crate::{client::ClientResult, rpc::msig_history::MsigTxResponse, ClientError, SafeClient};
+
+/// Polling configuration for watchers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchConfig {
+    /// Time between successive polls of the API
+    pub poll_interval: Duration,
+    /// Give up after this long. `None` to watch indefinitely
+    pub timeout: Option<Duration>,
+}
+
+impl Default for WatchConfig {
+    /// Poll every 10 seconds, giving up after an hour
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(10),
+            timeout: Some(Duration::from_secs(3600)),
+        }
+    }
+}
+
+/// Events observed by watchers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TxEvent {
+    /// An owner confirmed the transaction
+    NewConfirmation {
+        /// Safe tx hash of the confirmed transaction
+        safe_tx_hash: H256,
+        /// The confirming owner
+        owner: Address,
+    },
+    /// The transaction has enough confirmations to be executed
+    ThresholdReached {
+        /// Safe tx hash of the transaction
+        safe_tx_hash: H256,
+    },
+    /// The transaction was executed successfully
+    Executed {
+        /// Safe tx hash of the transaction
+        safe_tx_hash: H256,
+        /// Hash of the ethereum transaction that executed it
+        transaction_hash: Option<H256>,
+    },
+    /// The transaction was executed, but the inner call reverted. The nonce
+    /// is consumed
+    Reverted {
+        /// Safe tx hash of the transaction
+        safe_tx_hash: H256,
+        /// Hash of the ethereum transaction that executed it
+        transaction_hash: Option<H256>,
+    },
+    /// Another transaction at the same nonce was executed. This transaction
+    /// can no longer be executed
+    Replaced {
+        /// Safe tx hash of the replaced transaction
+        safe_tx_hash: H256,
+        /// Safe tx hash of the executed transaction
+        by: H256,
+    },
+}
+
+impl TxEvent {
+    /// The safe tx hash this event refers to
+    pub fn safe_tx_hash(&self) -> H256 {
+        match self {
+            TxEvent::NewConfirmation { safe_tx_hash, .. }
+            | TxEvent::ThresholdReached { safe_tx_hash }
+            | TxEvent::Executed { safe_tx_hash, .. }
+            | TxEvent::Reverted { safe_tx_hash, .. }
+            | TxEvent::Replaced { safe_tx_hash, .. } => *safe_tx_hash,
+        }
+    }
+
+    /// True if no further events will be emitted for the transaction
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            TxEvent::Executed { .. } | TxEvent::Reverted { .. } | TxEvent::Replaced { .. }
+        )
+    }
+}
+
+/// Last observed state of a transaction
+#[derive(Debug, Default)]
+struct TxState {
+    confirmations: HashSet<Address>,
+    threshold_reached: bool,
+    finished: bool,
+}
+
+impl TxState {
+    /// Update from the latest API response, returning any new events. When
+    /// `quiet`, existing confirmations are recorded without emitting events
+    fn update(&mut self, tx: &MsigTxResponse, threshold: u32, quiet: bool) -> Vec<TxEvent> {
+        let mut events = vec![];
+        if self.finished {
+            return events;
+        }
+        let safe_tx_hash = tx.safe_tx_hash;
+
+        for confirmation in tx.confirmations.iter() {
+            if self.confirmations.insert(confirmation.owner) && !quiet {
+                events.push(TxEvent::NewConfirmation {
+                    safe_tx_hash,
+                    owner: confirmation.owner,
+                });
+            }
+        }
+
+        let required = tx.confirmations_required.unwrap_or(threshold);
+        if !self.threshold_reached && self.confirmations.len() as u32 >= required {
+            self.threshold_reached = true;
+            events.push(TxEvent::ThresholdReached { safe_tx_hash });
+        }
+
+        if tx.is_executed {
+            self.finished = true;
+            let transaction_hash = tx.transaction_hash;
+            events.push(match tx.is_successful {
+                Some(false) => TxEvent::Reverted {
+                    safe_tx_hash,
+                    transaction_hash,
+                },
+                _ => TxEvent::Executed {
+                    safe_tx_hash,
+                    transaction_hash,
+                },
+            });
+        }
+        events
+    }
+
+    fn replace(&mut self, safe_tx_hash: H256, by: H256) -> Option<TxEvent> {
+        if self.finished {
+            return None;
+        }
+        self.finished = true;
+        Some(TxEvent::Replaced { safe_tx_hash, by })
+    }
+}
+
+fn timed_out(start: Instant, config: &WatchConfig) -> bool {
+    config.timeout.is_some_and(|t| start.elapsed() >= t)
+}
+
+impl SafeClient {
+    /// Find the executed transaction at `nonce`, if any
+    async fn executed_at_nonce(
+        &self,
+        safe_address: Address,
+        nonce: u64,
+    ) -> ClientResult<Option<MsigTxResponse>> {
+        Ok(self
+            .msig_history_builder()
+            .nonce(nonce)
+            .query(safe_address)
+            .await?
+            .results
+            .into_iter()
+            .find(|tx| tx.is_executed && tx.nonce == nonce))
+    }
+
+    /// Watch a single transaction, yielding events until it is executed or
+    /// replaced. Confirmations that exist when watching starts are not
+    /// reported. Yields [`ClientError::Timeout`] and ends if the configured
+    /// timeout elapses first
+    pub fn watch_transaction(
+        &self,
+        safe_tx_hash: H256,
+        config: WatchConfig,
+    ) -> impl tokio_stream::Stream<Item = ClientResult<TxEvent>> + '_ {
+        stream! {
+            tracing::debug!(safe_tx_hash = ?safe_tx_hash, "watching transaction");
+            let start = Instant::now();
+
+            let tx = self.transaction_info(safe_tx_hash).await?;
+            let threshold = self.safe_info(tx.safe).await?.threshold;
+
+            let mut state = TxState::default();
+            let mut tx = Some(tx);
+            let mut quiet = true;
+            loop {
+                let current = match tx.take() {
+                    Some(tx) => tx,
+                    None => self.transaction_info(safe_tx_hash).await?,
+                };
+
+                for event in state.update(&current, threshold, quiet) {
+                    yield Ok(event);
+                }
+                quiet = false;
+                if state.finished {
+                    return;
+                }
+
+                // if this tx was executed since the last poll, it is picked up
+                // as executed on the next poll rather than as replaced
+                let executed = self.executed_at_nonce(current.safe, current.nonce).await?;
+                match executed {
+                    Some(executed) if executed.safe_tx_hash != safe_tx_hash => {
+                        if let Some(event) = state.replace(safe_tx_hash, executed.safe_tx_hash) {
+                            yield Ok(event);
+                        }
+                        return;
+                    }
+                    _ => {}
+                }
+
+                if timed_out(start, &config) {
+                    yield Err(ClientError::Timeout(start.elapsed()));
+                    return;
+                }
+                tokio::time::sleep(config.poll_interval).await;
+            }
+        }
+    }
+
+    /// Watch all pending transactions of a Safe, including those proposed
+    /// after watching starts. Confirmations that exist when watching starts
+    /// are not reported. Runs until the configured timeout elapses, if any
+    pub fn watch_safe(
+        &self,
+        safe_address: Address,
+        config: WatchConfig,
+    ) -> impl tokio_stream::Stream<Item = ClientResult<TxEvent>> + '_ {
+        stream! {
+            tracing::debug!(safe_address = ?safe_address, "watching safe");
+            let start = Instant::now();
+
+            let info = self.safe_info(safe_address).await?;
+            let mut min_nonce = info.nonce;
+
+            // keyed by safe tx hash, with the nonce for pruning
+            let mut states: HashMap<H256, (u64, TxState)> = HashMap::new();
+            let mut quiet = true;
+            loop {
+                let txns: Vec<MsigTxResponse> = self
+                    .msig_history_builder()
+                    .min_nonce(min_nonce)
+                    .into_stream(safe_address)
+                    .filter(|tx| tx.as_ref().map_or(true, |tx| tx.nonce >= min_nonce))
+                    .collect::<ClientResult<_>>()
+                    .await?;
+
+                let executed: HashMap<u64, H256> = txns
+                    .iter()
+                    .filter(|tx| tx.is_executed)
+                    .map(|tx| (tx.nonce, tx.safe_tx_hash))
+                    .collect();
+
+                for tx in txns.iter() {
+                    let (_, state) = states
+                        .entry(tx.safe_tx_hash)
+                        .or_insert_with(|| (tx.nonce, TxState::default()));
+                    for event in state.update(tx, info.threshold, quiet) {
+                        yield Ok(event);
+                    }
+                    match executed.get(&tx.nonce) {
+                        Some(by) if *by != tx.safe_tx_hash => {
+                            if let Some(event) = state.replace(tx.safe_tx_hash, *by) {
+                                yield Ok(event);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+
+                // every txn below the highest executed nonce has been
+                // executed or replaced, and reported above
+                if let Some(nonce) = executed.keys().max() {
+                    min_nonce = min_nonce.max(nonce + 1);
+                    states.retain(|_, (nonce, _)| *nonce >= min_nonce);
+                }
+                quiet = false;
+
+                if timed_out(start, &config) {
+                    yield Err(ClientError::Timeout(start.elapsed()));
+                    return;
+                }
+                tokio::time::sleep(config.poll_interval).await;
+            }
+        }
+    }
+
+    /// Wait until the transaction is executed, returning its final state.
+    /// Execution may have reverted, check `is_successful`. Errors with
+    /// [`ClientError::Replaced`] if another transaction used its nonce
+    pub async fn wait_for_execution(
+        &self,
+        safe_tx_hash: H256,
+        config: WatchConfig,
+    ) -> ClientResult<MsigTxResponse> {
+        let events = self.watch_transaction(safe_tx_hash, config);
+        tokio::pin!(events);
+        while let Some(event) = events.next().await {
+            match event? {
+                TxEvent::Executed { .. } | TxEvent::Reverted { .. } => {
+                    return self.transaction_info(safe_tx_hash).await
+                }
+                TxEvent::Replaced { safe_tx_hash, by } => {
+                    return Err(ClientError::Replaced { safe_tx_hash, by })
+                }
+                _ => {}
+            }
+        }
+        Err(ClientError::Other(
+            "transaction watcher ended unexpectedly".to_owned(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rpc::msig_history::fixtures::msig_tx;
+
+    fn tx(executed: bool, confirmed_by: &[Address]) -> MsigTxResponse {
+        msig_tx(
+            Address::repeat_byte(0x5a),
+            3,
+            H256::repeat_byte(0xaa),
+            executed,
+            confirmed_by,
+        )
+    }
+
+    #[test]
+    fn it_updates_tx_state() {
+        let safe_tx_hash = H256::repeat_byte(0xaa);
+        let (a, b) = (Address::repeat_byte(0xa), Address::repeat_byte(0xb));
+        let mut state = TxState::default();
+
+        // existing confirmations are recorded silently
+        assert!(state.update(&tx(false, &[a]), 2, true).is_empty());
+        assert!(state.update(&tx(false, &[a]), 2, false).is_empty());
+
+        assert_eq!(
+            state.update(&tx(false, &[a, b]), 2, false),
+            vec![
+                TxEvent::NewConfirmation {
+                    safe_tx_hash,
+                    owner: b
+                },
+                TxEvent::ThresholdReached { safe_tx_hash },
+            ]
+        );
+        // threshold is reported once
+        assert!(state.update(&tx(false, &[a, b]), 2, false).is_empty());
+
+        let events = state.update(&tx(true, &[a, b]), 2, false);
+        assert_eq!(
+            events,
+            vec![TxEvent::Executed {
+                safe_tx_hash,
+                transaction_hash: None
+            }]
+        );
+        assert!(events[0].is_final());
+        assert!(state.finished);
+        assert!(state.update(&tx(true, &[a, b]), 2, false).is_empty());
+        assert_eq!(state.replace(safe_tx_hash, H256::zero()), None);
+    }
+
+    #[test]
+    fn it_replaces_pending_txns_once() {
+        let safe_tx_hash = H256::repeat_byte(0xaa);
+        let mut state = TxState::default();
+        assert!(state.update(&tx(false, &[]), 1, true).is_empty());
+        assert_eq!(
+            state.replace(safe_tx_hash, H256::zero()),
+            Some(TxEvent::Replaced {
+                safe_tx_hash,
+                by: H256::zero()
+            })
+        );
+        assert_eq!(state.replace(safe_tx_hash, H256::zero()), None);
+    }
+
+    #[tokio::test]
+    async fn it_ignores_executed_txns_at_other_nonces() {
+        use tokio_stream::StreamExt;
+
+        use crate::rpc::{
+            mock::{page, safe_info, MockService},
+            msig_history::fixtures::msig_tx_json,
+        };
+
+        let safe = Address::repeat_byte(0x5a);
+        let owner = Address::repeat_byte(0xa);
+        let pending = H256::repeat_byte(0xaa);
+        let service = MockService::start(move |path, _| {
+            if path.ends_with("/multisig-transactions/") {
+                // an executed txn at an older nonce, as on any Safe with
+                // history
+                (
+                    200,
+                    page(vec![msig_tx_json(
+                        safe,
+                        1,
+                        H256::repeat_byte(1),
+                        true,
+                        &[owner],
+                    )]),
+                )
+            } else if path.starts_with("/api/v1/multisig-transactions/") {
+                (200, msig_tx_json(safe, 3, pending, false, &[owner]))
+            } else {
+                (200, safe_info(safe, 3, 2, &[owner]))
+            }
+        })
+        .await;
+        let client = service.client(1);
+
+        let config = WatchConfig {
+            poll_interval: Duration::from_millis(10),
+            timeout: Some(Duration::from_millis(50)),
+        };
+        let events: Vec<_> = client.watch_transaction(pending, config).collect().await;
+        assert!(matches!(events.as_slice(), [Err(ClientError::Timeout(_))]));
+        assert!(service
+            .requests()
+            .iter()
+            .any(|r| r.contains("/multisig-transactions/?nonce=3")));
+    }
+
+    #[tokio::test]
+    async fn it_watches_from_the_safe_nonce() {
+        use tokio_stream::StreamExt;
+
+        use crate::rpc::{
+            mock::{page, safe_info, MockService},
+            msig_history::fixtures::msig_tx_json,
+        };
+
+        let safe = Address::repeat_byte(0x5a);
+        let owner = Address::repeat_byte(0xa);
+        let service = MockService::start(move |path, _| {
+            if path.ends_with("/multisig-transactions/") {
+                (
+                    200,
+                    page(vec![
+                        msig_tx_json(safe, 3, H256::repeat_byte(3), false, &[owner]),
+                        msig_tx_json(safe, 1, H256::repeat_byte(1), true, &[owner]),
+                    ]),
+                )
+            } else {
+                (200, safe_info(safe, 3, 2, &[owner]))
+            }
+        })
+        .await;
+        let client = service.client(1);
+
+        let config = WatchConfig {
+            poll_interval: Duration::from_millis(10),
+            timeout: Some(Duration::from_millis(50)),
+        };
+        // the txn executed before watching is not reported
+        let events: Vec<_> = client.watch_safe(safe, config).collect().await;
+        assert!(matches!(events.as_slice(), [Err(ClientError::Timeout(_))]));
+        assert!(service
+            .requests()
+            .iter()
+            .any(|r| r.contains("/multisig-transactions/?nonce__gte=3")));
+    }
+}
@@ -9,6 +9,7 @@ use safe_sdk::{
         common::{ChecksumAddress, Operations},
         propose::MetaTransactionData,
     },
+    safety::AllowedHazards,
 };
 
 pub const KEY: &str = "1c3a7cdd2270579847aaec11680312cbf4d3c36886232b413ab6529593228ec2";
@@ -61,10 +62,17 @@ async fn it_gets_balances() {
 async fn it_proposes() {
     let tx: MetaTransactionData = MetaTransactionData {
         to: ChecksumAddress::from(*ADDR),
-        value: 381832418u64,
+        value: 381832418u64.into(),
         data: Some("0xdeadbeefdeadbeef".parse().unwrap()),
         operation: Some(Operations::DelegateCall),
     };
 
-    dbg!(CLIENT.propose(tx, *SAFE).await.unwrap().nonce);
+    // delegatecall to an arbitrary contract must be explicitly allowed
+    let client = SigningClient::try_from_signer(WALLET.clone())
+        .unwrap()
+        .allow_hazards(AllowedHazards {
+            delegatecall: true,
+            ..Default::default()
+        });
+    dbg!(client.propose(tx, *SAFE).await.unwrap().nonce);
 }